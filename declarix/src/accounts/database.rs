@@ -0,0 +1,107 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use rusqlite::{Connection, Statement};
+
+use crate::manage_data::tools::create_db;
+
+pub struct AccountDatabase {
+    pub conn: Connection
+}
+
+impl AccountDatabase {
+    pub fn new() -> Self {
+        Self { conn: Connection::open(create_db("accounts")).unwrap() }
+    }
+
+    pub fn create_table(&self) {
+        for table in ["Users", "Groups"] {
+            self.conn.execute(&format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    name        TEXT NOT NULL,
+                    removal     TEXT NOT NULL,
+                    to_keep     BOOL
+                    );"
+            ), ()).unwrap();
+        }
+    }
+}
+
+pub struct AccountStatements<'conn> {
+    pub insert: Statement<'conn>,
+    pub update: Statement<'conn>,
+    pub select: Statement<'conn>,
+    pub zero: Statement<'conn>,
+    pub remove: Statement<'conn>,
+}
+
+impl <'conn>AccountStatements<'conn> {
+    pub fn users(conn: &'conn Connection) -> Self {
+        Self::prepare(conn, StatementPool::new("Users"))
+    }
+
+    pub fn groups(conn: &'conn Connection) -> Self {
+        Self::prepare(conn, StatementPool::new("Groups"))
+    }
+
+    fn prepare(conn: &'conn Connection, states: StatementPool) -> Self {
+        Self {
+            insert: conn.prepare(&states.insert).unwrap(),
+            update: conn.prepare(&states.update).unwrap(),
+            select: conn.prepare(&states.select).unwrap(),
+            zero: conn.prepare(&states.zero).unwrap(),
+            remove: conn.prepare(&states.remove).unwrap()
+        }
+    }
+}
+
+struct StatementPool {
+    insert: String,
+    update: String,
+    select: String,
+    zero: String,
+    remove: String,
+}
+
+impl StatementPool {
+    fn new(table: &str) -> Self {
+        Self {
+            update: format!(
+                "UPDATE {table}
+                    SET to_keep = 1, removal = ?2
+                    WHERE name = ?1
+                ;"),
+            insert: format!(
+                "INSERT INTO {table} (name, removal, to_keep)
+                    VALUES (?1, ?2, 1)
+                ;"),
+            select: format!(
+                "SELECT name, removal
+                FROM {table}
+                WHERE to_keep = 0
+                ;"),
+            zero: format!(
+                "UPDATE {table}
+                SET to_keep = 0
+                WHERE to_keep = 1
+                ;"),
+            remove: format!(
+                "DELETE FROM {table}
+                WHERE to_keep = 0
+                ;")
+        }
+    }
+}
@@ -0,0 +1,377 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::process::exit;
+use colored::{ColoredString, Colorize};
+use toml::Value;
+use users::{get_group_by_name, get_user_by_name, os::unix::UserExt};
+
+use shared::Ops;
+
+use crate::manage_data::tools::{confirm, get_table, missing_program, root_command};
+
+use super::database::{AccountDatabase, AccountStatements};
+
+enum AccountError {
+    InvalidField(&'static str, String, &'static str),
+    InvalidRemoval(&'static str, String, String),
+}
+
+impl fmt::Display for AccountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::InvalidField(kind, name, field) => {
+                writeln!(f, "\tInvalid {} for {} {}", field.red(), kind, name.red())?
+            },
+            Self::InvalidRemoval(kind, name, removal) => {
+                writeln!(f, "\tInvalid removal {} for {} {}", removal.red(), kind, name.red())?;
+                match *kind {
+                    "user" => writeln!(f, "\tExpected one of: {}", "keep, lock, delete".yellow())?,
+                    _ => writeln!(f, "\tExpected one of: {}", "keep, delete".yellow())?
+                }
+            }
+        }
+        exit(1);
+    }
+}
+
+/// What happens to an account once it is removed from the config.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Removal {
+    Keep,
+    Lock,
+    Delete,
+}
+
+impl Removal {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Lock => "lock",
+            Self::Delete => "delete",
+        }
+    }
+
+    fn from(removal: &str) -> Option<Self> {
+        match removal {
+            "keep" => Some(Self::Keep),
+            "lock" => Some(Self::Lock),
+            "delete" => Some(Self::Delete),
+            _ => None
+        }
+    }
+}
+
+/// Reads the fields shared by `[users.<name>]` and `[groups.<name>]`.
+struct Fields<'a> {
+    kind: &'static str,
+    name: &'a str,
+    table: toml::Table,
+}
+
+impl <'a>Fields<'a> {
+    fn new(kind: &'static str, name: &'a str, value: &Value) -> Self {
+        Self { kind, name, table: get_table(&format!("{kind}s.{name}"), value) }
+    }
+
+    fn invalid(&self, field: &'static str) -> ! {
+        println!("{}",AccountError::InvalidField(self.kind, self.name.to_string(), field));
+        exit(1)
+    }
+
+    fn id(&self, field: &'static str) -> Option<u32> {
+        self.table.get(field).map(|id| id.as_integer().and_then(|id| u32::try_from(id).ok()).unwrap_or_else(|| self.invalid(field)))
+    }
+
+    fn string(&self, field: &'static str) -> Option<String> {
+        self.table.get(field).map(|value| value.as_str().map(String::from).unwrap_or_else(|| self.invalid(field)))
+    }
+
+    fn boolean(&self, field: &'static str) -> bool {
+        self.table.get(field).map(|value| value.as_bool().unwrap_or_else(|| self.invalid(field))).unwrap_or(false)
+    }
+
+    fn removal(&self, allowed: &[Removal]) -> Removal {
+        let Some(removal) = self.string("removal") else {
+            return Removal::Keep
+        };
+        match Removal::from(&removal).filter(|removal| allowed.contains(removal)) {
+            Some(removal) => removal,
+            None => {
+                println!("{}",AccountError::InvalidRemoval(self.kind, self.name.to_string(), removal));
+                exit(1)
+            }
+        }
+    }
+}
+
+/// A group declared under `[groups.<name>]`.
+pub struct Group {
+    pub name: String,
+    pub gid: Option<u32>,
+    pub system: bool,
+    pub removal: Removal,
+}
+
+impl Group {
+    pub fn new(name: &str, value: &Value) -> Self {
+        let fields = Fields::new("group", name, value);
+        Self {
+            name: name.to_string(),
+            gid: fields.id("gid"),
+            system: fields.boolean("system"),
+            removal: fields.removal(&[Removal::Keep, Removal::Delete]),
+        }
+    }
+
+    fn add(&self) -> Vec<String> {
+        let mut args = vec!["groupadd".to_string()];
+        if let Some(gid) = self.gid {
+            args.extend(["-g".to_string(), gid.to_string()]);
+        }
+        if self.system {
+            args.push("-r".to_string());
+        }
+        args.push(self.name.to_string());
+        args
+    }
+
+    /// The command bringing the group in line with the config, if any.
+    fn command(&self) -> Option<Vec<String>> {
+        match get_group_by_name(&self.name) {
+            None => Some(self.add()),
+            Some(group) => match self.gid {
+                Some(gid) if gid != group.gid() => Some(vec!["groupmod".to_string(), "-g".to_string(), gid.to_string(), self.name.to_string()]),
+                _ => None
+            }
+        }
+    }
+}
+
+/// An account declared under `[users.<name>]`.
+pub struct User {
+    pub name: String,
+    pub uid: Option<u32>,
+    pub shell: Option<String>,
+    pub home: Option<String>,
+    pub groups: Vec<String>,
+    pub system: bool,
+    pub removal: Removal,
+}
+
+impl User {
+    pub fn new(name: &str, value: &Value) -> Self {
+        let fields = Fields::new("user", name, value);
+        let groups = match fields.table.get("groups") {
+            None => Vec::new(),
+            Some(Value::Array(groups)) => groups.iter()
+                .map(|group| group.as_str().map(String::from).unwrap_or_else(|| fields.invalid("groups")))
+                .collect(),
+            Some(_) => fields.invalid("groups")
+        };
+        Self {
+            name: name.to_string(),
+            uid: fields.id("uid"),
+            shell: fields.string("shell"),
+            home: fields.string("home"),
+            groups,
+            system: fields.boolean("system"),
+            removal: fields.removal(&[Removal::Keep, Removal::Lock, Removal::Delete]),
+        }
+    }
+
+    /// System accounts only get a home directory when one is given.
+    fn add(&self) -> Vec<String> {
+        let mut args = vec!["useradd".to_string()];
+        if let Some(uid) = self.uid {
+            args.extend(["-u".to_string(), uid.to_string()]);
+        }
+        if let Some(shell) = &self.shell {
+            args.extend(["-s".to_string(), shell.to_string()]);
+        }
+        if let Some(home) = &self.home {
+            args.extend(["-d".to_string(), home.to_string()]);
+        }
+        if !self.system || self.home.is_some() {
+            args.push("-m".to_string());
+        }
+        if self.system {
+            args.push("-r".to_string());
+        }
+        if !self.groups.is_empty() {
+            args.extend(["-G".to_string(), self.groups.join(",")]);
+        }
+        args.push(self.name.to_string());
+        args
+    }
+
+    /// The command bringing the account in line with the config, if any.
+    /// Supplementary groups are added, never taken away, and a home is only
+    /// moved after confirmation.
+    fn command(&self) -> Option<Vec<String>> {
+        let Some(user) = get_user_by_name(&self.name) else {
+            return Some(self.add())
+        };
+        let mut args = vec!["usermod".to_string()];
+        if let Some(uid) = self.uid.filter(|uid| *uid != user.uid()) {
+            args.extend(["-u".to_string(), uid.to_string()]);
+        }
+        if let Some(shell) = self.shell.as_ref().filter(|shell| user.shell().to_str() != Some(shell.as_str())) {
+            args.extend(["-s".to_string(), shell.to_string()]);
+        }
+        if let Some(home) = self.home.as_ref().filter(|home| user.home_dir().to_str() != Some(home.as_str())) {
+            if confirm(&format!("Move the home of {} from {} to {}?", self.name.red(), user.home_dir().display(), home)) {
+                args.extend(["-d".to_string(), home.to_string(), "-m".to_string()]);
+            }
+        }
+        let current: Vec<String> = user.groups().unwrap_or_default().iter()
+            .map(|group| group.name().to_string_lossy().to_string())
+            .collect();
+        let missing: Vec<String> = self.groups.iter().filter(|group| !current.contains(group)).cloned().collect();
+        if !missing.is_empty() {
+            args.extend(["-aG".to_string(), missing.join(",")]);
+        }
+        if args.len() == 1 {
+            return None
+        }
+        args.push(self.name.to_string());
+        Some(args)
+    }
+}
+
+/// The `[users]` and `[groups]` sections.
+pub struct Accounts {
+    pub users: Vec<User>,
+    pub groups: Vec<Group>,
+}
+
+impl Accounts {
+    pub fn new(users: Option<&Value>, groups: Option<&Value>) -> Self {
+        let table = |title: &str, value: Option<&Value>| value.map(|value| get_table(title, value)).unwrap_or_default();
+        Self {
+            users: table("users", users).iter().map(|(name, user)| User::new(name, user)).collect(),
+            groups: table("groups", groups).iter().map(|(name, group)| Group::new(name, group)).collect(),
+        }
+    }
+
+    fn prefix() -> ColoredString {
+        "[accounts]".cyan()
+    }
+
+    /// Runs an account command through declarixRoot.
+    fn run(args: &[String]) {
+        println!("{} {}", Self::prefix(), args.join(" "));
+        root_command(Ops::Account, args);
+    }
+
+    /// Creates and updates groups before users so that accounts can join
+    /// them, then handles whatever was removed from the config.
+    pub fn structure(&self) {
+        if let Some(reason) = missing_program("useradd") {
+            if !self.users.is_empty() || !self.groups.is_empty() {
+                println!("{}",format!("Skipped accounts: {reason}").yellow());
+            }
+            return
+        }
+        let db = AccountDatabase::new();
+        db.conn.execute("BEGIN TRANSACTION", ()).unwrap();
+        db.create_table();
+        let mut groups = AccountStatements::groups(&db.conn);
+        let mut users = AccountStatements::users(&db.conn);
+        for group in &self.groups {
+            if let Some(command) = group.command() {
+                Self::run(&command);
+            }
+            Self::track(&mut groups, &group.name, group.removal);
+        }
+        for user in &self.users {
+            if let Some(command) = user.command() {
+                Self::run(&command);
+            }
+            Self::track(&mut users, &user.name, user.removal);
+        }
+        for (name, removal) in Self::removed(&mut users) {
+            match removal {
+                Removal::Lock if get_user_by_name(&name).is_some() && confirm(&format!("Lock user {}?", name.red())) => {
+                    Self::run(&["usermod".to_string(), "-L".to_string(), "-e".to_string(), "1".to_string(), name])
+                },
+                Removal::Delete if get_user_by_name(&name).is_some() && confirm(&format!("Delete user {}?", name.red())) => {
+                    Self::run(&["userdel".to_string(), name])
+                },
+                _ => {}
+            }
+        }
+        for (name, removal) in Self::removed(&mut groups) {
+            if removal == Removal::Delete && get_group_by_name(&name).is_some() && confirm(&format!("Delete group {}?", name.red())) {
+                Self::run(&["groupdel".to_string(), name])
+            }
+        }
+        db.conn.execute("COMMIT TRANSACTION", ()).unwrap();
+    }
+
+    fn track(statements: &mut AccountStatements, name: &str, removal: Removal) {
+        if statements.update.execute((name, removal.as_str())).unwrap() == 0 {
+            statements.insert.execute((name, removal.as_str())).unwrap();
+        }
+    }
+
+    /// Accounts no longer in the config along with what should happen to
+    /// them, after which they are no longer tracked.
+    fn removed(statements: &mut AccountStatements) -> Vec<(String, Removal)> {
+        let removed = statements.select.query_map([], |row| {
+            let name: String = row.get(0)?;
+            let removal: String = row.get(1)?;
+            Ok((name, Removal::from(&removal).unwrap_or(Removal::Keep)))
+        }).unwrap().map(|removed| removed.unwrap()).collect();
+        statements.remove.execute([]).unwrap();
+        statements.zero.execute([]).unwrap();
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Group, Removal, User};
+
+    #[test]
+    fn accounts_build_commands() {
+        let config: toml::Value = toml::from_str(r#"
+            [users.media]
+            uid = 980
+            system = true
+            shell = "/usr/sbin/nologin"
+            groups = ["audio", "video"]
+            removal = "lock"
+
+            [users.alice]
+            home = "/srv/alice"
+
+            [groups.docker]
+            gid = 970
+            system = true
+        "#).unwrap();
+        let media = User::new("media", &config["users"]["media"]);
+        assert_eq!(media.add(), ["useradd", "-u", "980", "-s", "/usr/sbin/nologin", "-r", "-G", "audio,video", "media"]);
+        assert_eq!(media.removal, Removal::Lock);
+        let alice = User::new("alice", &config["users"]["alice"]);
+        assert_eq!(alice.add(), ["useradd", "-d", "/srv/alice", "-m", "alice"]);
+        assert_eq!(alice.removal, Removal::Keep);
+        let docker = Group::new("docker", &config["groups"]["docker"]);
+        assert_eq!(docker.add(), ["groupadd", "-g", "970", "-r", "docker"]);
+    }
+}
@@ -21,7 +21,7 @@ use term_size::dimensions;
 use colored::Colorize;
 use toml::{Table, Value};
 
-use crate::{database::database::{Database, PreparedStatements}, installation::init::{Err, Install}, manage_data::tools::get_table, services::services::Service, structures::structs::{Construct, Set, Setting}};
+use crate::{accounts::users::Accounts, database::database::{Database, PreparedStatements}, host::{kernel::Kernel, machine::Machine}, installation::init::{Err, Install}, manage_data::tools::{answer, get_table, Answer}, services::services::Service, structures::structs::{Construct, Set, Setting}};
 
 
 pub struct Connect {
@@ -30,7 +30,12 @@ pub struct Connect {
     link: (bool, Vec<String>),
     install: (bool, Vec<String>),
     service: (bool, Vec<String>),
+    accounts: bool,
+    kernel: bool,
+    machine: bool,
     force: Force,
+    no_remove: bool,
+    profiles: (bool, Vec<String>),
     mode: Mode,
     pub vec: (Vec<String>, bool, Set)
 }
@@ -43,6 +48,7 @@ enum Force {
 
 enum OptionError<'a> {
     InvalidOption(&'a str),
+    MissingValue(&'a str),
     NoConfigPath,
     InvalidConfigPath(&'a str),
     ConfigNotExist(&'a PathBuf)
@@ -56,6 +62,9 @@ impl <'a>fmt::Display for OptionError<'a> {
                 writeln!(f, "\tInvalid option: {}", option.red())?;
                 writeln!(f,"\tUse `{}` for a list of correct options.","-h".blue())?
             },
+            Self::MissingValue(option) => {
+                writeln!(f, "\tNo value was provided for: {}", option.red())?
+            },
             Self::NoConfigPath => {
                 writeln!(f, "\tA config path was not specified.")?;
             },
@@ -71,11 +80,61 @@ impl <'a>fmt::Display for OptionError<'a> {
     }
 }
 
+pub struct SubArgs {
+    pub positional: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl SubArgs {
+    fn new(args: &[String], values: &[&str], flags: &[&str]) -> Self {
+        let mut sub = Self {
+            positional: Vec::new(),
+            options: Vec::new(),
+            flags: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("-") {
+                sub.positional.push(arg.to_string());
+            } else if values.contains(&arg.as_str()) || arg == "-c" || arg == "--config" {
+                match args.next() {
+                    Some(value) => sub.options.push((arg.to_string(), value.to_string())),
+                    None => println!("{}",OptionError::MissingValue(arg))
+                }
+            } else if flags.contains(&arg.as_str()) || arg == "-y" || arg == "--yes" {
+                sub.flags.push(arg.to_string());
+            } else {
+                println!("{}",OptionError::InvalidOption(arg))
+            }
+        }
+        sub
+    }
+
+    pub fn option(&self, names: &[&str]) -> Option<&str> {
+        self.options.iter().rev()
+            .find(|(name, _)| names.contains(&name.as_str()))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn options(&self, names: &[&str]) -> Vec<&str> {
+        self.options.iter()
+            .filter(|(name, _)| names.contains(&name.as_str()))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub fn flag(&self, names: &[&str]) -> bool {
+        self.flags.iter().any(|flag| names.contains(&flag.as_str()))
+    }
+}
+
 enum Mode {
     Link,
     Install,
     Config,
     Service,
+    Profile,
     None,
 }
 
@@ -121,21 +180,58 @@ impl Connect {
             link: (false, Vec::new()),
             install: (false, Vec::new()),
             force: Force::None,
+            no_remove: false,
+            profiles: (false, Vec::new()),
             mode: Mode::None,
             conf: PathBuf::from("/etc/declarix/declarix.toml"),
             vec: (Vec::new(), true, Set::None),
-            service: (false, Vec::new())
+            service: (false, Vec::new()),
+            accounts: false,
+            kernel: false,
+            machine: false
         }
     }
 
     pub fn everything(&mut self) -> Result<(), Err> {
         let args: Vec<String> = args().collect();
+        if let Some(command) = args.get(1) {
+            match command.as_str() {
+                "adopt" => {
+                    self.adopt(&args[2..]);
+                    return Ok(())
+                },
+                "add" => {
+                    self.add(&args[2..]);
+                    return Ok(())
+                },
+                "remove" => {
+                    self.remove(&args[2..]);
+                    return Ok(())
+                },
+                "import" => {
+                    self.import(&args[2..]);
+                    return Ok(())
+                },
+                "upgrade" => {
+                    return self.upgrade(&args[2..])
+                },
+                "clean" => {
+                    return self.clean(&args[2..])
+                },
+                "status" => {
+                    self.status(&args[2..]);
+                    return Ok(())
+                },
+                &_ => {}
+            }
+        }
         if args.get(1).is_some() {
             for arg in &args[1..] {
                 if arg.starts_with("-") {
                     match arg.as_str() {
                         "-h" | "--help" => {
-                            println!("{}",self.help_text())
+                            println!("{}",self.help_text());
+                            return Ok(())
                         },
                         "-c" | "--config" => {
                             self.config_file.0 = true;
@@ -145,6 +241,18 @@ impl Connect {
                             self.service.0 = true;
                             self.mode = Mode::Service;
                         }
+                        "-a" | "--accounts" => {
+                            self.accounts = true;
+                            self.mode = Mode::None;
+                        }
+                        "-k" | "--kernel" => {
+                            self.kernel = true;
+                            self.mode = Mode::None;
+                        }
+                        "-m" | "--machine" => {
+                            self.machine = true;
+                            self.mode = Mode::None;
+                        }
                         "-l" | "--link" => {
                             self.link.0 = true;
                             self.mode = Mode::Link;
@@ -161,6 +269,16 @@ impl Connect {
                                 self.force = Force::NoConfirm;
                             }
                         }
+                        "-p" | "--profile" => {
+                            self.profiles.0 = true;
+                            self.mode = Mode::Profile;
+                        }
+                        "--no-remove" => {
+                            self.no_remove = true;
+                        }
+                        "-y" | "--yes" => {
+                            self.force = Force::NoConfirm;
+                        }
                         &_ => {
                             println!("{}",OptionError::InvalidOption(arg))
                         }
@@ -183,6 +301,9 @@ impl Connect {
                         Mode::Service => {
                             self.service.1.push(arg.to_string().to_lowercase());
                         },
+                        Mode::Profile => {
+                            self.profiles.1.push(arg.to_string().to_lowercase());
+                        },
                         Mode::None => {
                             println!("{}",OptionError::InvalidOption(arg))
                         }
@@ -199,6 +320,20 @@ impl Connect {
                     println!("{}",OptionError::NoConfigPath);
                 }
             }
+            match self.force {
+                Force::Confirm => answer(Answer::Force),
+                Force::NoConfirm => answer(Answer::Yes),
+                Force::None => {}
+            }
+            // -c, -p, -f, -y and --no-remove only modify a run; without a phase flag every phase runs.
+            if !self.link.0 && !self.install.0 && !self.service.0 && !self.accounts && !self.kernel && !self.machine {
+                self.link.0 = true;
+                self.install.0 = true;
+                self.service.0 = true;
+                self.accounts = true;
+                self.kernel = true;
+                self.machine = true;
+            }
             let conf = fs::read_to_string(&self.conf).unwrap();
             let conf:Table = toml::from_str(&conf).unwrap();
             if self.link.0 {
@@ -229,18 +364,39 @@ impl Connect {
             if self.install.0 {
                 let installation = conf.get("install");
                 let mut installer = Install::new();
+                installer.managers(conf.get("managers"));
+                installer.repositories(conf.get("repositories"));
+                installer.no_remove = self.no_remove;
+                installer.only = self.install.1.clone();
+                installer.profiles(self.profiles.0.then(|| self.profiles.1.clone()));
                 if let Some(installation) = installation {
                     for (title, inst) in get_table("install", installation) {
-                        if self.install.1.is_empty() || self.install.1.contains(&title.to_lowercase()) {
+                        if installer.selected(&title) {
                             installer.matches(&title, &inst)?
                         }
                     }
                     installer.structure()
+                } else {
+                    installer.sync_repositories()
                 }
             }
 
+            if self.accounts {
+                Accounts::new(conf.get("users"), conf.get("groups")).structure();
+            }
+
+            if self.kernel {
+                Kernel::new(conf.get("kernel")).structure();
+            }
+
+            if self.machine {
+                Machine::new(conf.get("machine")).structure();
+            }
+
             if self.service.0 {
-                let service = Service::new();
+                let mut service = Service::new();
+                service.directory = self.conf.parent().unwrap().to_path_buf();
+                service.managers = conf.get("managers").map(|managers| get_table("managers", managers)).unwrap_or_default();
                 let services = conf.get("services");
                 if let Some(services) = services {
                     for (title, s) in get_table("services", services) {
@@ -249,6 +405,11 @@ impl Connect {
                         }
                     }
                 }
+                service.dropped(services, &self.service.1);
+                if self.service.1.is_empty() || self.service.1.contains(&"timers".to_string()) {
+                    service.timers(conf.get("timers"));
+                }
+                service.report();
             }
         } else {
             let conf = fs::read_to_string(&self.conf).unwrap();
@@ -267,22 +428,61 @@ impl Connect {
             self.vec = construct.vec;
 
             let mut installer = Install::new();
+            installer.managers(conf.get("managers"));
+            installer.repositories(conf.get("repositories"));
             if let Some(install) = conf.get("install") {
                 for (title, inst) in get_table("install", &install) {
                     installer.matches(&title, &inst)?
                 }
                 installer.structure()
+            } else {
+                installer.sync_repositories()
             }
 
-            let services = Service::new();
+            Accounts::new(conf.get("users"), conf.get("groups")).structure();
+            Kernel::new(conf.get("kernel")).structure();
+            Machine::new(conf.get("machine")).structure();
+
+            let mut services = Service::new();
+            services.directory = self.conf.parent().unwrap().to_path_buf();
+            services.managers = conf.get("managers").map(|managers| get_table("managers", managers)).unwrap_or_default();
             if let Some(service) = conf.get("services") {
                 for (title, serv) in get_table("services", &service) {
                     services.match_service(&title, &serv)
                 }
             }
+            services.dropped(conf.get("services"), &[]);
+            services.timers(conf.get("timers"));
+            services.report();
         }
         Ok(())
     }
+    pub fn sub_args(&mut self, args: &[String], values: &[&str], flags: &[&str]) -> SubArgs {
+        let sub = SubArgs::new(args, values, flags);
+        if let Some(config_path) = sub.option(&["-c", "--config"]) {
+            if !config_path.ends_with(".toml") {
+                println!("{}",OptionError::InvalidConfigPath(config_path));
+            }
+            self.conf = PathBuf::from(config_path);
+            if !self.conf.exists() {
+                println!("{}",OptionError::ConfigNotExist(&self.conf));
+            }
+        }
+        if sub.flag(&["-y", "--yes"]) {
+            answer(Answer::Yes);
+        }
+        sub
+    }
+
+    pub fn conf_path(&self) -> &PathBuf {
+        &self.conf
+    }
+
+    pub fn read_config(&self) -> Table {
+        let conf = fs::read_to_string(&self.conf).unwrap();
+        toml::from_str(&conf).unwrap()
+    }
+
     fn paths_process(&mut self, title: Title, config_path: &Option<&Value>, statements: &mut PreparedStatements, aliases: &Table, paths: Option<&Value>, construct: &mut Construct) {
         construct.title = title.clone();
         match &title {
@@ -304,6 +504,7 @@ Declarix
 A declarative system management tool for various platforms. 
 Usage:
     declarix [Options] [Sub-Options] <COMMANDS>
+    declarix <COMMAND> [Arguments] [Options]
 
 Options: 
     -h, --help          Show this help message
@@ -319,6 +520,21 @@ Options:
 
     -s, --services      Only enables/disables based on provided config
     (Options explained under \"Service\")
+
+    -a, --accounts      Only creates/updates the [users] and [groups] in your config
+
+    -k, --kernel        Only writes the sysctl settings and kernel modules in [kernel]
+
+    -m, --machine       Only applies the hostname, timezone, locale and keyboard in [machine]
+
+    -p, --profile       Enables package groups whose condition names these profiles, until -p is given again
+    (Options explained under \"Install\")
+
+    --no-remove         Never uninstall packages that were dropped from your config
+
+    -f, --force         Removes without asking when declarix is not run from a terminal
+
+    -fc, -y, --yes      Agrees to every removal without asking
             
 Link:
     -l, --link <list of paths>      
@@ -336,8 +552,17 @@ Install:
         
         Providing a list of managers allows you to select which managers to use.
         
+        Group names select every manager listed in that group.
+
+    -p, --profile <list of profiles>
+
+        Enables groups with a matching condition, e.g. if = \"desktop\" or if.profile = \"desktop\"
+
+        The profiles are remembered for later runs without -p. -p alone turns them all off again.
+
     Example:
         declarix -i vsc flatpak paru
+        declarix -i dev -p desktop
 
 Service:
     -s, --services <list of service managers>
@@ -345,9 +570,57 @@ Service:
         Alone will use all service managers provided in your config
 
         Providing a list of service managers allows you to select which managers to use.
+        Supported managers are systemd, openrc, runit, s6 and dinit.
+        [timers] are selected with \"timers\".
 
     Example:
         declarix -s systemd
+
+Commands:
+    adopt <path> [--set <set>] [--setting <setting>]
+        Moves an existing file or directory into the config repository, replaces it with a managed link and adds it to your config.
+
+        --set is one of home, root or other (Default: home)
+        --setting is one of link, recursive or copy (Default: link)
+
+    Example:
+        declarix adopt ~/.config/foo --set home --setting link
+
+    add package <manager> <packages>
+    add service <manager> <user|root> <services>
+    add link <source> <destination> [--set <set>] [--setting <setting>]
+        Adds entries to your config, keeping its comments and formatting.
+
+    remove package <manager> <packages>
+    remove service <manager> <user|root> <services>
+    remove link <source> [--set <set>] [--setting <setting>]
+        Removes entries from your config. They are uninstalled, disabled or unlinked on the next run.
+
+    Example:
+        declarix add package paru neovim ripgrep
+        declarix add service systemd user mpd
+        declarix remove link .zshrc --set home
+
+    import packages --manager <manager> [--write]
+        Lists explicitly installed packages that are missing from your config.
+
+        --write adds the missing packages to your config instead of printing them.
+
+    Example:
+        declarix import packages --manager pacman --write
+
+    upgrade [<managers or groups>] [--profile <profile>]
+        Upgrades everything installed by the managers in your config.
+
+    clean [<managers or groups>] [--profile <profile>] [--yes]
+        Clears package caches and removes dependencies that are no longer needed.
+
+    Example:
+        declarix upgrade
+        declarix clean paru flatpak
+
+    status
+        Shows the [machine] settings that differ from your config.
         ";
 
         let terminal_width = dimensions().map(|w|w.0).unwrap_or(80 as usize);
@@ -0,0 +1,160 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::{fs, path::Path, process::exit};
+use colored::Colorize;
+use toml::{Table, Value};
+
+use crate::manage_data::tools::{get_array, get_buffer, get_table, record_change, remove_root, write_root};
+
+enum KernelError {
+    InvalidValue(String),
+    InvalidModule(&'static str, String),
+}
+
+impl fmt::Display for KernelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::InvalidValue(key) => {
+                writeln!(f, "\tInvalid sysctl value for {}", key.red())?;
+                writeln!(f, "\tExpected a string, number or boolean: {}", "\"vm.swappiness\" = 10".yellow())?
+            },
+            Self::InvalidModule(list, module) => {
+                writeln!(f, "\tInvalid module in kernel {}: {}", list, module.red())?;
+                writeln!(f, "\tExpected a module name: {}", "\"br_netfilter\"".yellow())?
+            }
+        }
+        exit(1);
+    }
+}
+
+const SYSCTL: &str = "/etc/sysctl.d/99-declarix.conf";
+const MODULES: &str = "/etc/modules-load.d/declarix.conf";
+const BLACKLIST: &str = "/etc/modprobe.d/declarix-blacklist.conf";
+
+/// The `[kernel]` section. Each part is written to a file owned by declarix,
+/// which is removed again once the part is empty or gone.
+pub struct Kernel {
+    pub sysctl: Vec<(String, String)>,
+    pub modules: Vec<String>,
+    pub blacklist: Vec<String>,
+}
+
+impl Kernel {
+    pub fn new(kernel: Option<&Value>) -> Self {
+        let kernel = kernel.map(|kernel| get_table("kernel", kernel)).unwrap_or_default();
+        let mut sysctl = Vec::new();
+        if let Some(table) = kernel.get("sysctl") {
+            Self::flatten("", &get_table("kernel.sysctl", table), &mut sysctl);
+        }
+        let modules = |list: &'static str| -> Vec<String> {
+            kernel.get(list).map(|modules| get_array(&format!("kernel.{list}"), modules)).unwrap_or_default().iter()
+                .map(|module| match module.as_str() {
+                    Some(module) if !module.is_empty() && !module.contains(char::is_whitespace) => module.to_string(),
+                    _ => {
+                        println!("{}",KernelError::InvalidModule(list, module.to_string()));
+                        exit(1)
+                    }
+                })
+                .collect()
+        };
+        Self {
+            sysctl,
+            modules: modules("modules"),
+            blacklist: modules("blacklist"),
+        }
+    }
+
+    /// Dotted keys such as `net.ipv4.ip_forward = 1` are read by TOML as
+    /// nested tables, so they are joined back together.
+    fn flatten(prefix: &str, table: &Table, sysctl: &mut Vec<(String, String)>) {
+        for (key, value) in table {
+            let key = if prefix.is_empty() { key.to_string() } else { format!("{prefix}.{key}") };
+            match value {
+                Value::Table(table) => Self::flatten(&key, table, sysctl),
+                Value::String(value) => sysctl.push((key, value.to_string())),
+                Value::Integer(value) => sysctl.push((key, value.to_string())),
+                Value::Float(value) => sysctl.push((key, value.to_string())),
+                Value::Boolean(value) => sysctl.push((key, (*value as u8).to_string())),
+                _ => {
+                    println!("{}",KernelError::InvalidValue(key));
+                    exit(1)
+                }
+            }
+        }
+    }
+
+    fn render(lines: Vec<String>) -> Option<String> {
+        if lines.is_empty() {
+            return None
+        }
+        Some(format!("# Managed by declarix, changes will be overwritten.\n{}\n", lines.join("\n")))
+    }
+
+    /// The contents of each file, or `None` when it should not exist.
+    pub fn files(&self) -> [(&'static str, Option<String>); 3] {
+        [
+            (SYSCTL, Self::render(self.sysctl.iter().map(|(key, value)| format!("{key} = {value}")).collect())),
+            (MODULES, Self::render(self.modules.clone())),
+            (BLACKLIST, Self::render(self.blacklist.iter().map(|module| format!("blacklist {module}")).collect())),
+        ]
+    }
+
+    /// Writes the files that changed, removes the ones no longer needed and
+    /// reloads sysctl settings when they changed.
+    pub fn structure(&self) {
+        let mut reload = false;
+        for (path, contents) in self.files() {
+            let path = Path::new(path);
+            let changed = match contents {
+                Some(contents) if fs::read_to_string(path).ok().as_deref() != Some(contents.as_str()) => write_root(path, contents),
+                None if path.exists() => remove_root(path),
+                _ => false
+            };
+            if changed {
+                println!("{} {}","[kernel]".cyan(),format!("Updated {}",path.display()).green());
+                record_change(path);
+                reload |= path == Path::new(SYSCTL);
+            }
+        }
+        if reload {
+            get_buffer("sudo", &vec!["sysctl".to_string(), "--system".to_string()], &Vec::new());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Kernel;
+
+    #[test]
+    fn kernel_renders_files() {
+        let config: toml::Value = toml::from_str(r#"
+            [kernel]
+            modules = ["br_netfilter"]
+
+            [kernel.sysctl]
+            "vm.swappiness" = 10
+            net.ipv4.ip_forward = true
+        "#).unwrap();
+        let files = Kernel::new(config.get("kernel")).files();
+        assert_eq!(files[0].1.as_deref(), Some("# Managed by declarix, changes will be overwritten.\nnet.ipv4.ip_forward = 1\nvm.swappiness = 10\n"));
+        assert_eq!(files[1].1.as_deref(), Some("# Managed by declarix, changes will be overwritten.\nbr_netfilter\n"));
+        assert_eq!(files[2].1, None);
+    }
+}
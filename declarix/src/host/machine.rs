@@ -0,0 +1,266 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::{fs, path::Path, process::exit};
+use colored::Colorize;
+use toml::Value;
+
+use crate::{connect::Connect, manage_data::tools::{get_array, get_prefixed_buffer, get_table, missing_program, write_root}};
+
+enum MachineError {
+    InvalidField(&'static str),
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::InvalidField(field) => {
+                writeln!(f, "\tInvalid {} in [machine]", field.red())?;
+                match *field {
+                    "locales" => writeln!(f, "\tExpected an array: {}", "[\"en_US.UTF-8\", \"de_DE.UTF-8\"]".yellow())?,
+                    _ => writeln!(f, "\tExpected a string: {}", "timezone = \"Europe/Berlin\"".yellow())?
+                }
+            }
+        }
+        exit(1);
+    }
+}
+
+const LOCALE_GEN: &str = "/etc/locale.gen";
+
+/// A setting that differs from the config.
+pub struct Drift {
+    pub setting: &'static str,
+    pub current: String,
+    pub wanted: String,
+}
+
+/// The `[machine]` section.
+pub struct Machine {
+    pub hostname: Option<String>,
+    pub timezone: Option<String>,
+    pub locales: Vec<String>,
+    pub lang: Option<String>,
+    pub keymap: Option<String>,
+    pub layout: Option<String>,
+}
+
+/// The last `KEY=value` assignment of `key` in a shell style file.
+fn assignment(contents: &str, key: &str) -> Option<String> {
+    contents.lines()
+        .filter_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+        .map(|value| value.trim().trim_matches('"').to_string())
+        .next_back()
+}
+
+/// Reads `KEY=value` from the first of `files` that sets it.
+fn variable(files: &[&str], key: &str) -> Option<String> {
+    files.iter().filter_map(|file| fs::read_to_string(file).ok()).find_map(|contents| assignment(&contents, key))
+}
+
+/// The `XkbLayout` option of an xorg.conf `InputClass` section.
+fn xkb_layout(conf: &str) -> Option<String> {
+    conf.lines()
+        .find(|line| line.split_whitespace().nth(1) == Some("\"XkbLayout\""))
+        .and_then(|line| line.split('"').nth(3).map(String::from))
+}
+
+impl Machine {
+    pub fn new(machine: Option<&Value>) -> Self {
+        let machine = machine.map(|machine| get_table("machine", machine)).unwrap_or_default();
+        let string = |field: &'static str| machine.get(field).map(|value| value.as_str().map(String::from).unwrap_or_else(|| {
+            println!("{}",MachineError::InvalidField(field));
+            exit(1)
+        }));
+        let locales = machine.get("locales").map(|locales| get_array("machine.locales", locales)).unwrap_or_default().iter()
+            .map(|locale| locale.as_str().map(String::from).unwrap_or_else(|| {
+                println!("{}",MachineError::InvalidField("locales"));
+                exit(1)
+            }))
+            .collect();
+        Self {
+            hostname: string("hostname"),
+            timezone: string("timezone"),
+            locales,
+            lang: string("lang"),
+            keymap: string("keymap"),
+            layout: string("layout"),
+        }
+    }
+
+    fn hostname() -> String {
+        fs::read_to_string("/etc/hostname").map(|hostname| hostname.trim().to_string()).unwrap_or_default()
+    }
+
+    fn timezone() -> String {
+        fs::read_link("/etc/localtime").ok()
+            .and_then(|zone| zone.to_str().and_then(|zone| zone.split_once("zoneinfo/")).map(|(_, zone)| zone.to_string()))
+            .unwrap_or_default()
+    }
+
+    fn lang() -> String {
+        variable(&["/etc/locale.conf", "/etc/default/locale"], "LANG").unwrap_or_default()
+    }
+
+    fn keymap() -> String {
+        variable(&["/etc/vconsole.conf"], "KEYMAP")
+            .or_else(|| variable(&["/etc/conf.d/keymaps"], "keymap"))
+            .unwrap_or_default()
+    }
+
+    /// The X11 layout, as written by `localectl` or Debian's keyboard file.
+    fn layout() -> String {
+        fs::read_to_string("/etc/X11/xorg.conf.d/00-keyboard.conf").ok()
+            .and_then(|conf| xkb_layout(&conf))
+            .or_else(|| variable(&["/etc/default/keyboard"], "XKBLAYOUT"))
+            .unwrap_or_default()
+    }
+
+    /// Locales that are not enabled in `/etc/locale.gen`. Distributions
+    /// without it ship their locales prebuilt.
+    fn missing_locales(&self) -> Vec<String> {
+        let Ok(locale_gen) = fs::read_to_string(LOCALE_GEN) else {
+            return Vec::new()
+        };
+        let enabled: Vec<&str> = locale_gen.lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        self.locales.iter().filter(|locale| !enabled.contains(&locale.as_str())).cloned().collect()
+    }
+
+    /// `/etc/locale.gen` with the missing locales uncommented, or appended
+    /// when the file does not list them.
+    fn locale_gen(contents: &str, missing: &[String]) -> String {
+        let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+        for locale in missing {
+            let commented = lines.iter_mut().find(|line| {
+                line.trim_start().starts_with('#') && line.trim_start_matches(['#', ' ']).split_whitespace().next() == Some(locale)
+            });
+            match commented {
+                Some(line) => *line = line.trim_start_matches(['#', ' ']).to_string(),
+                None => {
+                    let charset = locale.split_once('.').map(|(_, charset)| charset).unwrap_or("ISO-8859-1");
+                    lines.push(format!("{locale} {charset}"))
+                }
+            }
+        }
+        format!("{}\n", lines.join("\n"))
+    }
+
+    /// Locales come first, as localed refuses to set a `LANG` that is not
+    /// generated yet.
+    pub fn drift(&self) -> Vec<Drift> {
+        let mut drift = Vec::new();
+        let missing = self.missing_locales();
+        if !missing.is_empty() {
+            drift.push(Drift { setting: "locales", current: String::new(), wanted: missing.join(", ") })
+        }
+        let mut compare = |setting: &'static str, wanted: &Option<String>, current: fn() -> String| {
+            if let Some(wanted) = wanted {
+                let current = current();
+                if current != *wanted {
+                    drift.push(Drift { setting, current, wanted: wanted.to_string() })
+                }
+            }
+        };
+        compare("hostname", &self.hostname, Self::hostname);
+        compare("timezone", &self.timezone, Self::timezone);
+        compare("lang", &self.lang, Self::lang);
+        compare("keymap", &self.keymap, Self::keymap);
+        compare("layout", &self.layout, Self::layout);
+        drift
+    }
+
+    fn run(program: &str, args: &[&str]) -> Result<(), String> {
+        if let Some(reason) = missing_program(program) {
+            return Err(reason)
+        }
+        let args: Vec<String> = [program].iter().chain(args).map(|arg| arg.to_string()).collect();
+        get_prefixed_buffer(&"[machine]".cyan(), "sudo", &args, &[])
+    }
+
+    /// Applies every setting that drifted from the config.
+    pub fn structure(&self) {
+        for drift in self.drift() {
+            let applied = match drift.setting {
+                "hostname" => Self::run("hostnamectl", &["set-hostname", &drift.wanted]),
+                "timezone" => Self::run("timedatectl", &["set-timezone", &drift.wanted]),
+                "lang" => Self::run("localectl", &["set-locale", &format!("LANG={}", drift.wanted)]),
+                "keymap" => Self::run("localectl", &["set-keymap", "--no-convert", &drift.wanted]),
+                "layout" => Self::run("localectl", &["set-x11-keymap", "--no-convert", &drift.wanted]),
+                _ => {
+                    let contents = fs::read_to_string(LOCALE_GEN).unwrap();
+                    if write_root(Path::new(LOCALE_GEN), Self::locale_gen(&contents, &self.missing_locales())) {
+                        Self::run("locale-gen", &[])
+                    } else {
+                        Err(format!("{LOCALE_GEN} could not be written."))
+                    }
+                }
+            };
+            match applied {
+                Ok(()) => println!("{} {}","[machine]".cyan(),format!("Set {} to {}",drift.setting,drift.wanted).green()),
+                Err(err) => println!("{} {}","[machine]".cyan(),format!("Could not set {} to {}: {err}",drift.setting,drift.wanted).red())
+            }
+        }
+    }
+}
+
+impl Connect {
+    /// Shows which `[machine]` settings differ from the config.
+    pub fn status(&mut self, args: &[String]) {
+        self.sub_args(args, &[], &[]);
+        let conf = self.read_config();
+        let machine = Machine::new(conf.get("machine"));
+        let drift = machine.drift();
+        if drift.is_empty() {
+            println!("{}","[machine] matches your config.".green());
+            return
+        }
+        for drift in drift {
+            match drift.setting {
+                "locales" => println!("{} {}: {} not generated","[machine]".cyan(),drift.setting,drift.wanted.red()),
+                _ => println!("{} {}: {} (config: {})","[machine]".cyan(),drift.setting,drift.current.red(),drift.wanted.green())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assignment, xkb_layout, Machine};
+
+    #[test]
+    fn locale_gen_enables_locales() {
+        let contents = "# en_US ISO-8859-1\n# en_US.UTF-8 UTF-8\nde_DE.UTF-8 UTF-8\n";
+        let missing = ["en_US.UTF-8".to_string(), "fr_FR.UTF-8".to_string()];
+        assert_eq!(Machine::locale_gen(contents, &missing), "# en_US ISO-8859-1\nen_US.UTF-8 UTF-8\nde_DE.UTF-8 UTF-8\nfr_FR.UTF-8 UTF-8\n");
+    }
+
+    #[test]
+    fn settings_are_read_from_files() {
+        let locale = "# generated\nLANG=en_US.UTF-8\nLC_TIME=\"de_DE.UTF-8\"\n";
+        assert_eq!(assignment(locale, "LANG").unwrap(), "en_US.UTF-8");
+        assert_eq!(assignment(locale, "LC_TIME").unwrap(), "de_DE.UTF-8");
+        assert!(assignment(locale, "LC").is_none());
+        assert_eq!(assignment("XKBMODEL=\"pc105\"\nXKBLAYOUT=\"de\"\n", "XKBLAYOUT").unwrap(), "de");
+        let conf = "Section \"InputClass\"\n        Identifier \"system-keyboard\"\n        MatchIsKeyboard \"on\"\n        Option \"XkbLayout\" \"us,de\"\n        Option \"XkbVariant\" \",nodeadkeys\"\nEndSection\n";
+        assert_eq!(xkb_layout(conf).unwrap(), "us,de");
+        assert!(xkb_layout("Section \"InputClass\"\nEndSection\n").is_none());
+    }
+}
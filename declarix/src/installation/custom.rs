@@ -0,0 +1,192 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::process::exit;
+use colored::Colorize;
+use toml::Value;
+
+use crate::manage_data::tools::get_table;
+
+use super::installers::{Output, Prog, Query, Version};
+
+enum CustomError<'a> {
+    MissingCommand(&'a str, &'static str),
+    InvalidCommand(&'a str, &'static str),
+    InvalidBool(&'a str, &'static str),
+    InvalidParser(&'a str, String),
+    MissingRegex(&'a str),
+    InvalidPin(&'a str),
+    InvalidPath(&'a str),
+}
+
+impl <'a>fmt::Display for CustomError<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::MissingCommand(manager, key) => {
+                writeln!(f, "\t[managers.{}] is missing {}", manager.red(), key.red())?
+            },
+            Self::InvalidCommand(manager, key) => {
+                writeln!(f, "\tInvalid command for {} in [managers.{}]", key.red(), manager)?;
+                writeln!(f, "\tExpected a string or an array: {}", "\"tool install\"".yellow())?
+            },
+            Self::InvalidBool(manager, key) => {
+                writeln!(f, "\tInvalid value for {} in [managers.{}]", key.red(), manager)?;
+                writeln!(f, "\tExpected {} or {}", "true".yellow(), "false".yellow())?
+            },
+            Self::InvalidParser(manager, parser) => {
+                writeln!(f, "\tInvalid parser {} in [managers.{}]", parser.red(), manager)?;
+                writeln!(f, "\tExpected one of: {}, {} or {}", "lines".yellow(), "column".yellow(), "regex".yellow())?
+            },
+            Self::MissingRegex(manager) => {
+                writeln!(f, "\t[managers.{}] uses the regex parser without a {}", manager.red(), "regex".red())?
+            },
+            Self::InvalidPin(manager) => {
+                writeln!(f, "\tInvalid pin in [managers.{}]", manager.red())?;
+                writeln!(f, "\tExpected a separator such as {} or a flag such as {}", "\"=\"".yellow(), "\"--version\"".yellow())?
+            },
+            Self::InvalidPath(manager) => {
+                writeln!(f, "\tInvalid path in [managers.{}]", manager.red())?;
+                writeln!(f, "\tExpected a string: {}", "\"/opt/tool/bin/tool\"".yellow())?
+            }
+        }
+        exit(1);
+    }
+}
+
+/// A package manager declared by the user under `[managers.<name>]`.
+#[derive(Clone, PartialEq)]
+pub struct Custom {
+    install: Vec<String>,
+    uninstall: Vec<String>,
+    list: Vec<String>,
+    explicit: Option<Vec<String>>,
+    upgrade: Option<Vec<String>>,
+    clean: Option<Vec<String>>,
+    escalate: bool,
+    batch: bool,
+    parser: Output,
+    pin: Version,
+}
+
+impl Custom {
+    /// The `path` of a `[managers.<name>]` table, where its tool is found
+    /// when it is not in `PATH`.
+    pub fn path(name: &str, definition: &Value) -> Option<String> {
+        match definition.get("path") {
+            None => None,
+            Some(Value::String(path)) => Some(path.to_string()),
+            Some(_) => {
+                println!("{}",CustomError::InvalidPath(name));
+                exit(1)
+            }
+        }
+    }
+
+    pub fn new(name: &str, definition: &Value) -> Self {
+        let table = get_table(&format!("managers.{name}"), definition);
+        let command = |key: &'static str| table.get(key).map(|value| {
+            let command: Option<Vec<String>> = match value {
+                Value::String(command) => Some(command.split_whitespace().map(String::from).collect()),
+                Value::Array(command) => command.iter().map(|arg| arg.as_str().map(String::from)).collect(),
+                _ => None
+            };
+            match command {
+                Some(command) if !command.is_empty() => command,
+                _ => {
+                    println!("{}",CustomError::InvalidCommand(name, key));
+                    exit(1)
+                }
+            }
+        });
+        let required = |key: &'static str| command(key).unwrap_or_else(|| {
+            println!("{}",CustomError::MissingCommand(name, key));
+            exit(1)
+        });
+        let boolean = |key: &'static str, default: bool| match table.get(key) {
+            None => default,
+            Some(Value::Boolean(value)) => *value,
+            Some(_) => {
+                println!("{}",CustomError::InvalidBool(name, key));
+                exit(1)
+            }
+        };
+        let parser = match table.get("parser").and_then(Value::as_str).unwrap_or("lines") {
+            "lines" => Output::Lines,
+            "column" => Output::FirstColumn,
+            "regex" => match table.get("regex").and_then(Value::as_str) {
+                Some(regex) => Output::Regex(regex.to_string()),
+                None => {
+                    println!("{}",CustomError::MissingRegex(name));
+                    exit(1)
+                }
+            },
+            parser => {
+                println!("{}",CustomError::InvalidParser(name, parser.to_string()));
+                exit(1)
+            }
+        };
+        let pin = match table.get("pin") {
+            None => Version::Unsupported,
+            Some(Value::String(pin)) if pin.starts_with('-') && pin.len() > 1 => Version::Flag(pin.to_string()),
+            Some(Value::String(pin)) => Version::Suffix(pin.to_string()),
+            Some(_) => {
+                println!("{}",CustomError::InvalidPin(name));
+                exit(1)
+            }
+        };
+        Self {
+            install: required("install"),
+            uninstall: required("uninstall"),
+            list: required("list"),
+            explicit: command("explicit"),
+            upgrade: command("upgrade"),
+            clean: command("clean"),
+            escalate: boolean("escalate", false),
+            batch: boolean("batch", true),
+            parser,
+            pin,
+        }
+    }
+
+    pub fn prog(&self, title: &str) -> Prog {
+        let split = |command: &[String]| match self.escalate {
+            true => ("sudo".to_string(), command.to_vec()),
+            false => (command[0].to_string(), command[1..].to_vec())
+        };
+        let (prog, install) = split(&self.install);
+        let (uninstaller, uninstall) = split(&self.uninstall);
+        let explicit = self.explicit.as_ref().unwrap_or(&self.list);
+        let mut prog = Prog::new(&prog, title,
+            install.iter().map(String::as_str),
+            uninstall.iter().map(String::as_str),
+            Query::new(self.list.iter().map(String::as_str), self.parser.clone()),
+            Query::new(explicit.iter().map(String::as_str), self.parser.clone()));
+        if uninstaller != prog.prog {
+            prog.uninstaller = Some(uninstaller);
+        }
+        prog.batch = self.batch;
+        prog.version = self.pin.clone();
+        let escalated = |command: &Vec<String>| match self.escalate {
+            true => [vec!["sudo".to_string()], command.to_vec()].concat(),
+            false => command.to_vec()
+        };
+        prog.upgrade = self.upgrade.iter().map(escalated).collect();
+        prog.clean = self.clean.iter().map(escalated).collect();
+        prog
+    }
+}
@@ -16,7 +16,7 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
 use rusqlite::{Connection, Statement};
 
-use crate::manage_data::tools::create_db;
+use crate::{installation::distro::Distro, manage_data::tools::create_db};
 
 pub struct PackDatabase {
     pub conn: Connection
@@ -35,6 +35,42 @@ impl PackDatabase {
                 to_keep     BOOL
                 );"
         ), ()).unwrap();
+        self.conn.execute(&format!(
+            "CREATE TABLE IF NOT EXISTS Repositories (
+                repository  TEXT NOT NULL,
+                manager     TEXT NOT NULL,
+                to_keep     BOOL
+                );"
+        ), ()).unwrap();
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS Holds (
+                package     TEXT NOT NULL,
+                manager     TEXT NOT NULL,
+                to_keep     BOOL
+                );"
+        , ()).unwrap();
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS Profiles (
+                profile     TEXT NOT NULL
+                );"
+        , ()).unwrap();
+        // pacman, apt, dnf and zypper packages used to be stored under "sudo".
+        if let Some(manager) = Distro::detect().and_then(|distro| distro.manager()) {
+            self.conn.execute("UPDATE Packages SET manager = ?1 WHERE manager = 'sudo'", [manager]).unwrap();
+        }
+    }
+
+    /// The profiles given with the last `-p`.
+    pub fn profiles(&self) -> Vec<String> {
+        let mut profiles = self.conn.prepare("SELECT profile FROM Profiles").unwrap();
+        profiles.query_map([], |row| row.get(0)).unwrap().map(|profile| profile.unwrap()).collect()
+    }
+
+    pub fn set_profiles(&self, profiles: &[String]) {
+        self.conn.execute("DELETE FROM Profiles", ()).unwrap();
+        for profile in profiles {
+            self.conn.execute("INSERT INTO Profiles (profile) VALUES (?1)", [profile]).unwrap();
+        }
     }
 }
 
@@ -44,17 +80,30 @@ pub struct PackStatements<'conn> {
     pub select: Statement<'conn>,
     pub zero: Statement<'conn>,
     pub remove: Statement<'conn>,
+    pub delete: Statement<'conn>,
 }
 
 impl <'conn>PackStatements<'conn> {
     pub fn new(conn: &'conn Connection) -> Self {
-        let states = StatementPool::new();
+        Self::prepare(conn, StatementPool::new("Packages", "package"))
+    }
+
+    pub fn repositories(conn: &'conn Connection) -> Self {
+        Self::prepare(conn, StatementPool::new("Repositories", "repository"))
+    }
+
+    pub fn holds(conn: &'conn Connection) -> Self {
+        Self::prepare(conn, StatementPool::new("Holds", "package"))
+    }
+
+    fn prepare(conn: &'conn Connection, states: StatementPool) -> Self {
         Self {
             insert: conn.prepare(&states.insert).unwrap(),
             update: conn.prepare(&states.update).unwrap(),
             select: conn.prepare(&states.select).unwrap(),
             zero: conn.prepare(&states.zero).unwrap(),
-            remove: conn.prepare(&states.remove).unwrap()
+            remove: conn.prepare(&states.remove).unwrap(),
+            delete: conn.prepare(&states.delete).unwrap()
         }
     }
 }
@@ -65,33 +114,38 @@ struct StatementPool {
     select: String,
     zero: String,
     remove: String,
+    delete: String,
 }
 
 impl StatementPool {
-    fn new() -> Self {
+    fn new(table: &str, column: &str) -> Self {
         Self {
             update: format!(
-                "UPDATE Packages
+                "UPDATE {table}
                     SET to_keep = 1
-                    WHERE package = ?1 AND manager = ?2
+                    WHERE {column} = ?1 AND manager = ?2
                 ;"),
             insert: format!(
-                "INSERT INTO Packages (package, manager, to_keep)
+                "INSERT INTO {table} ({column}, manager, to_keep)
                     VALUES (?1, ?2, 1)
                 ;"),
             select: format!(
-                "SELECT package
-                FROM Packages
+                "SELECT {column}
+                FROM {table}
                 WHERE manager = ?1 AND to_keep = 0
                 ;"),
             zero: format!(
-                "UPDATE Packages
+                "UPDATE {table}
                 SET to_keep = 0
                 WHERE to_keep = 1 AND manager = ?1
                 ;"),
             remove: format!(
-                "DELETE FROM Packages
+                "DELETE FROM {table}
                 WHERE to_keep = 0 AND manager = ?1
+                ;"),
+            delete: format!(
+                "DELETE FROM {table}
+                WHERE {column} = ?1 AND manager = ?2
                 ;")
 
         }
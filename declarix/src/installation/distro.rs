@@ -0,0 +1,98 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::fs;
+
+pub struct Distro {
+    pub id: String,
+    pub like: Vec<String>,
+}
+
+impl Distro {
+    pub fn detect() -> Option<Self> {
+        ["/etc/os-release", "/usr/lib/os-release"].iter()
+            .find_map(|path| fs::read_to_string(path).ok())
+            .map(|release| Self::parse(&release))
+    }
+
+    fn parse(release: &str) -> Self {
+        let mut distro = Self { id: String::new(), like: Vec::new() };
+        for line in release.lines() {
+            if let Some((key, value)) = line.split_once("=") {
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'').to_lowercase();
+                match key.trim() {
+                    "ID" => distro.id = value,
+                    "ID_LIKE" => distro.like = value.split_whitespace().map(String::from).collect(),
+                    &_ => {}
+                }
+            }
+        }
+        distro
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.id.as_str()).chain(self.like.iter().map(String::as_str))
+    }
+
+    pub fn manager(&self) -> Option<&'static str> {
+        self.ids().find_map(|id| {
+            match id {
+                "arch" => Some("pacman"),
+                "debian" | "ubuntu" => Some("apt"),
+                "fedora" | "rhel" | "centos" => Some("dnf"),
+                "void" => Some("xbps"),
+                "alpine" => Some("apk"),
+                "gentoo" => Some("emerge"),
+                id if id == "suse" || id.starts_with("opensuse") || id == "sles" => Some("zypper"),
+                &_ => None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Distro;
+
+    #[test]
+    fn os_release_is_parsed() {
+        let distro = Distro::parse("NAME=\"Linux Mint\"\nID=linuxmint\nID_LIKE=\"ubuntu debian\"\nVERSION_ID=\"21.3\"\n");
+        assert_eq!(distro.id, "linuxmint");
+        assert_eq!(distro.like, ["ubuntu", "debian"]);
+        assert_eq!(distro.ids().collect::<Vec<&str>>(), ["linuxmint", "ubuntu", "debian"]);
+        let distro = Distro::parse("ID='Arch'\n# comment\nBUILD_ID=rolling\n");
+        assert_eq!(distro.id, "arch");
+        assert!(distro.like.is_empty());
+        assert_eq!(Distro::parse("").manager(), None);
+    }
+
+    #[test]
+    fn ids_map_to_managers() {
+        let manager = |release: &str| Distro::parse(release).manager();
+        assert_eq!(manager("ID=arch"), Some("pacman"));
+        assert_eq!(manager("ID=endeavouros\nID_LIKE=arch"), Some("pacman"));
+        assert_eq!(manager("ID=debian"), Some("apt"));
+        assert_eq!(manager("ID=pop\nID_LIKE=\"ubuntu debian\""), Some("apt"));
+        assert_eq!(manager("ID=fedora"), Some("dnf"));
+        assert_eq!(manager("ID=rocky\nID_LIKE=\"rhel centos fedora\""), Some("dnf"));
+        assert_eq!(manager("ID=opensuse-tumbleweed\nID_LIKE=\"opensuse suse\""), Some("zypper"));
+        assert_eq!(manager("ID=sles"), Some("zypper"));
+        assert_eq!(manager("ID=void"), Some("xbps"));
+        assert_eq!(manager("ID=alpine"), Some("apk"));
+        assert_eq!(manager("ID=gentoo"), Some("emerge"));
+        assert_eq!(manager("ID=nixos"), None);
+    }
+}
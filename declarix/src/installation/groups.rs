@@ -0,0 +1,193 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::{fs, process::exit};
+use colored::Colorize;
+use toml::Value;
+
+use crate::manage_data::tools::get_table;
+
+use super::init::{Err, Install};
+
+enum GroupError {
+    InvalidCondition(String),
+    UnknownCondition(String, String),
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::InvalidCondition(group) => {
+                writeln!(f, "\tInvalid condition for [install.groups.{}]", group.red())?;
+                writeln!(f, "\tExpected a string, an array or a table: {}", "if.os = \"arch\"".yellow())?
+            },
+            Self::UnknownCondition(group, key) => {
+                writeln!(f, "\tUnknown condition {} for [install.groups.{}]", key.red(), group)?;
+                writeln!(f, "\tExpected one of: {}, {} or {}", "os".yellow(), "host".yellow(), "profile".yellow())?
+            }
+        }
+        exit(1);
+    }
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .map(|host| host.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn values(group: &str, value: &Value) -> Vec<String> {
+    match value {
+        Value::String(value) => vec![value.to_lowercase()],
+        Value::Array(values) => values.iter().map(|value| match value {
+            Value::String(value) => value.to_lowercase(),
+            _ => {
+                println!("{}",GroupError::InvalidCondition(group.to_string()));
+                exit(1)
+            }
+        }).collect(),
+        _ => {
+            println!("{}",GroupError::InvalidCondition(group.to_string()));
+            exit(1)
+        }
+    }
+}
+
+impl Install {
+    /// Expands `[install.groups.<name>]` into the managers it lists when its
+    /// `if` condition holds, or when the group is selected by name with `-i`.
+    pub fn groups(&mut self, groups: &Value) -> Result<(), Err> {
+        for (group, entries) in get_table("groups", groups) {
+            let entries = get_table(&format!("groups.{group}"), &entries);
+            let selected = self.only.contains(&group.to_lowercase());
+            if !selected && !entries.get("if").is_none_or(|condition| self.condition(&group, condition)) {
+                continue
+            }
+            for (title, installer) in entries.iter().filter(|(title, _)| title.as_str() != "if") {
+                if selected || self.selected(title) {
+                    self.matches(title, installer)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn condition(&self, group: &str, condition: &Value) -> bool {
+        let host = hostname().to_lowercase();
+        let os: Vec<&str> = self.distro.iter().flat_map(|distro| distro.ids()).collect();
+        match condition {
+            Value::Table(table) => table.iter().all(|(key, value)| {
+                let values = values(group, value);
+                match key.as_str() {
+                    "os" => values.iter().any(|value| os.contains(&value.as_str())),
+                    "host" => values.contains(&host),
+                    "profile" => values.iter().any(|value| self.profiles.contains(value)),
+                    key => {
+                        println!("{}",GroupError::UnknownCondition(group.to_string(), key.to_string()));
+                        exit(1)
+                    }
+                }
+            }),
+            _ => values(group, condition).iter().any(|tag| {
+                self.profiles.contains(tag) || *tag == host || os.contains(&tag.as_str())
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use toml::Value;
+
+    use super::hostname;
+    use crate::installation::{distro::Distro, init::Install};
+
+    fn expanded(install: &mut Install, groups: &str) -> Vec<(String, Vec<String>)> {
+        let groups: Value = toml::from_str(groups).unwrap();
+        install.matches("groups", &groups).ok().unwrap();
+        install.gather.iter()
+            .map(|(title, _, packages)| (title.to_string(), packages.iter().map(|package| package.as_str().unwrap().to_string()).collect()))
+            .collect()
+    }
+
+    fn install(profiles: &[&str]) -> Install {
+        let mut install = Install::new();
+        install.distro = Some(Distro { id: "endeavouros".to_string(), like: vec!["arch".to_string()] });
+        install.profiles = profiles.iter().map(|profile| profile.to_string()).collect();
+        install
+    }
+
+    #[test]
+    fn groups_expand_when_their_condition_holds() {
+        let groups = format!(r#"
+            [always]
+            cargo = ["ripgrep"]
+
+            [arch]
+            if.os = "arch"
+            cargo = ["paru"]
+
+            [debian]
+            if.os = ["debian", "ubuntu"]
+            cargo = ["apt-cache"]
+
+            [here]
+            if = "{}"
+            pipx = ["black"]
+
+            [elsewhere]
+            if.host = "not-this-host"
+            pipx = ["ruff"]
+
+            [both]
+            if = {{ os = "arch", host = "not-this-host" }}
+            pipx = ["mypy"]
+        "#, hostname());
+        let gathered = expanded(&mut install(&[]), &groups);
+        assert_eq!(gathered, [
+            ("cargo".to_string(), vec!["ripgrep".to_string(), "paru".to_string()]),
+            ("pipx".to_string(), vec!["black".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn profiles_select_groups() {
+        let groups = r#"
+            [desktop]
+            if.profile = "desktop"
+            cargo = ["alacritty"]
+
+            [gaming]
+            if = ["gaming", "streaming"]
+            pipx = ["protonup"]
+
+            [server]
+            if.profile = "server"
+            cargo = ["bottom"]
+        "#;
+        assert!(expanded(&mut install(&[]), groups).is_empty());
+        assert_eq!(expanded(&mut install(&["desktop", "streaming"]), groups), [
+            ("cargo".to_string(), vec!["alacritty".to_string()]),
+            ("pipx".to_string(), vec!["protonup".to_string()]),
+        ]);
+        let mut selected = install(&[]);
+        selected.only = vec!["server".to_string()];
+        assert_eq!(expanded(&mut selected, groups), [("cargo".to_string(), vec!["bottom".to_string()])]);
+    }
+}
@@ -0,0 +1,129 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::process::exit;
+use colored::Colorize;
+
+use crate::{connect::Connect, manage_data::{config::{ConfigFile, Entry}, tools::{find_program, get_table}}};
+
+use super::{distro::Distro, init::{Install, Manager}, package::Package};
+
+enum ImportError {
+    InvalidKind(String),
+    NoManager,
+    InvalidManager(String),
+    NotInstalled(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::InvalidKind(kind) => {
+                writeln!(f, "\tCannot import: {}", kind.red())?;
+                writeln!(f, "\tExpected: {}", "packages".yellow())?
+            },
+            Self::NoManager => {
+                writeln!(f, "\tA manager was not specified. Use {}", "--manager <manager>".yellow())?
+            },
+            Self::InvalidManager(manager) => {
+                writeln!(f, "\tInvalid manager: {}", manager.red())?
+            },
+            Self::NotInstalled(manager) => {
+                writeln!(f, "\t{} is not installed on your system.", manager.red())?
+            }
+        }
+        exit(1);
+    }
+}
+
+impl Connect {
+    pub fn import(&mut self, args: &[String]) {
+        let args = self.sub_args(args, &["-m", "--manager"], &["-w", "--write"]);
+        match args.positional.first().map(String::as_str) {
+            Some("packages") => {},
+            kind => println!("{}",ImportError::InvalidKind(kind.unwrap_or_default().to_string()))
+        }
+        let Some(title) = args.option(&["-m", "--manager"]).map(str::to_lowercase) else {
+            println!("{}",ImportError::NoManager);
+            return
+        };
+        let conf = self.read_config();
+        let mut system = Install::new();
+        system.managers(conf.get("managers"));
+        let Ok(manager) = system.manager(&title) else {
+            println!("{}",ImportError::InvalidManager(title.to_string()));
+            return
+        };
+
+        let mut key = title.to_string();
+        let mut listed = Vec::new();
+        if let Some(install) = conf.get("install") {
+            let native = system.distro.as_ref().and_then(Distro::manager).and_then(|native| Manager::new(native).ok());
+            let mut entries = Vec::new();
+            for (name, packages) in get_table("install", install) {
+                if name.eq_ignore_ascii_case("groups") {
+                    // Group packages are listed whether or not the group's condition holds.
+                    for (group, table) in get_table("groups", &packages) {
+                        for (name, packages) in get_table(&format!("groups.{group}"), &table) {
+                            if name != "if" {
+                                entries.push((name, packages, true))
+                            }
+                        }
+                    }
+                } else if !name.eq_ignore_ascii_case("protected") {
+                    entries.push((name, packages, false))
+                }
+            }
+            for (name, packages, grouped) in entries {
+                if name.eq_ignore_ascii_case("system") {
+                    if native.as_ref() == Some(&manager) {
+                        listed.extend(Package::names(&system.system_packages(&name, &packages)));
+                    }
+                } else if system.manager(&name).is_ok_and(|m| m == manager) {
+                    listed.extend(Package::names(&Install::packages(&name, &packages)));
+                    if !grouped {
+                        key = name;
+                    }
+                }
+            }
+        }
+
+        let explicit = manager.prog(&title).explicit;
+        if find_program(explicit.prog()).is_none() {
+            println!("{}",ImportError::NotInstalled(explicit.prog().to_string()))
+        }
+        let installed = explicit.run();
+        let missing: Vec<String> = installed.into_iter().filter(|package| !listed.contains(package)).collect();
+
+        if missing.is_empty() {
+            println!("{}", format!("[install.{key}] already lists every installed package.").green());
+        } else if args.flag(&["-w", "--write"]) {
+            let mut config = ConfigFile::open(self.conf_path());
+            for package in &missing {
+                config.add(&Entry::Package(&key, package));
+            }
+            config.save().unwrap();
+            println!("Added {} packages to [install.{}]", missing.len().to_string().green(), key);
+        } else {
+            println!("{}", format!("Missing from [install.{key}]:").bold().underline());
+            for package in missing {
+                println!("{package}")
+            }
+        }
+    }
+}
@@ -14,14 +14,13 @@ GNU General Public License for more details.
 You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
-use std::path::PathBuf;
-use colored::Colorize;
-use regex::Regex;
-use toml::Value;
+use std::{fs, mem::take, process::Command, thread};
+use colored::{ColoredString, Colorize};
+use toml::{map::Map, Value};
 
-use crate::manage_data::tools::{checker, convert_to_string, get_array, get_buffer};
+use crate::manage_data::tools::{confirm, get_array, get_prefixed_buffer, get_string, get_table, log_path, missing_program};
 
-use super::{database::database::{PackDatabase, PackStatements}, installers::{Arch, Builder, Debian, Fedora, Flatpak, OpenSUSE, Prog, Vsc}};
+use super::{custom::Custom, package::Package, repositories::{Repositories, MANAGERS}, database::database::{PackDatabase, PackStatements}, distro::Distro, installers::{Alpine, Arch, Brew, Builder, Cargo, Debian, Fedora, Flatpak, Gem, Gentoo, Go, Hold, Naming, Nix, Npm, OpenSUSE, Pipx, Prog, Removal, Snap, Version, Void, Vsc}};
 
 #[derive(Debug)]
 pub enum Err {
@@ -29,6 +28,7 @@ pub enum Err {
     InvalidPackage
 }
 
+#[derive(PartialEq)]
 pub enum Manager {
     Arch,
     Debian,
@@ -37,10 +37,82 @@ pub enum Manager {
     Vsc,
     Vscodium,
     Flatpak,
+    Cargo,
+    Pipx,
+    Npm,
+    Go,
+    Gem,
+    Void,
+    Alpine,
+    Gentoo,
+    Nix,
+    Snap,
+    Brew,
+    Custom(Box<Custom>),
+}
+
+impl Manager {
+    pub fn new(title: &str) -> Result<Self, Err> {
+        match title.to_lowercase().as_str() {
+            "paru" | "yay" | "pacman" => Ok(Self::Arch),
+            "apt" => Ok(Self::Debian),
+            "zypper" => Ok(Self::OpenSUSE),
+            "dnf" => Ok(Self::Fedora),
+            "vsc" | "code" | "vscode" => Ok(Self::Vsc),
+            "vscodium" => Ok(Self::Vscodium),
+            "flatpak" => Ok(Self::Flatpak),
+            "cargo" => Ok(Self::Cargo),
+            "pipx" => Ok(Self::Pipx),
+            "npm" => Ok(Self::Npm),
+            "go" => Ok(Self::Go),
+            "gem" => Ok(Self::Gem),
+            "xbps" => Ok(Self::Void),
+            "apk" => Ok(Self::Alpine),
+            "emerge" | "portage" => Ok(Self::Gentoo),
+            "nix" => Ok(Self::Nix),
+            "snap" => Ok(Self::Snap),
+            "brew" | "homebrew" => Ok(Self::Brew),
+            &_ => Err(Err::InvalidPackage)
+        }
+    }
+
+    pub fn prog(&self, title: &str) -> Prog {
+        match self {
+            Self::Arch => Arch::new(title).prog,
+            Self::Debian => Debian::new(title).prog,
+            Self::OpenSUSE => OpenSUSE::new(title).prog,
+            Self::Fedora => Fedora::new(title).prog,
+            Self::Vsc => Vsc::new("code").prog,
+            Self::Vscodium => Vsc::new(title).prog,
+            Self::Flatpak => Flatpak::new(title).prog,
+            Self::Cargo => Cargo::new(title).prog,
+            Self::Pipx => Pipx::new(title).prog,
+            Self::Npm => Npm::new(title).prog,
+            Self::Go => Go::new(title).prog,
+            Self::Gem => Gem::new(title).prog,
+            Self::Void => Void::new(title).prog,
+            Self::Alpine => Alpine::new(title).prog,
+            Self::Gentoo => Gentoo::new(title).prog,
+            Self::Nix => Nix::new(title).prog,
+            Self::Snap => Snap::new(title).prog,
+            Self::Brew => Brew::new(title).prog,
+            Self::Custom(custom) => custom.prog(title),
+        }
+    }
 }
 
 pub struct Install {
     pub gather: Vec<(String, Manager, Vec<Value>)>,
+    pub system: Vec<Value>,
+    pub distro: Option<Distro>,
+    pub managers: Map<String, Value>,
+    pub repositories: Repositories,
+    pub protected: Vec<String>,
+    pub keep: Vec<String>,
+    pub after: Vec<(String, Vec<String>)>,
+    pub no_remove: bool,
+    pub profiles: Vec<String>,
+    pub only: Vec<String>,
     pub arch: i32,
     pub vsc: i32
 }
@@ -49,147 +121,850 @@ impl Install {
     pub fn new() -> Self {
         Self {
             gather: Vec::new(),
+            system: Vec::new(),
+            distro: Distro::detect(),
+            managers: Map::new(),
+            repositories: Repositories::new(None),
+            protected: Vec::new(),
+            keep: Vec::new(),
+            after: Vec::new(),
+            no_remove: false,
+            profiles: Vec::new(),
+            only: Vec::new(),
             arch: 0,
             vsc: 0,
         }
     }
+    pub fn managers(&mut self, managers: Option<&Value>) {
+        if let Some(managers) = managers {
+            self.managers = get_table("managers", managers);
+        }
+    }
+
+    /// Profiles given with `-p` are saved, and runs without `-p` use the saved
+    /// ones, so the groups they enable are not taken as dropped.
+    pub fn profiles(&mut self, profiles: Option<Vec<String>>) {
+        let db = PackDatabase::new();
+        db.create_table();
+        self.profiles = match profiles {
+            Some(profiles) => {
+                db.set_profiles(&profiles);
+                profiles
+            },
+            None => db.profiles()
+        };
+    }
+
+    pub fn repositories(&mut self, repositories: Option<&Value>) {
+        self.repositories = Repositories::new(repositories);
+        self.repositories.programs = MANAGERS.iter()
+            .filter_map(|manager| Some((manager.to_string(), self.path(manager)?)))
+            .collect();
+    }
+
+    /// A `[managers.<name>]` table holding only a `path` overrides where a
+    /// built in manager is found instead of declaring a new one.
+    pub fn manager(&self, title: &str) -> Result<Manager, Err> {
+        match self.managers.iter().find(|(name, _)| name.eq_ignore_ascii_case(title)) {
+            Some((_, definition)) if definition.as_table().is_some_and(|table| table.keys().all(|key| key == "path")) => Manager::new(title),
+            Some((name, definition)) => Ok(Manager::Custom(Box::new(Custom::new(name, definition)))),
+            None => Manager::new(title)
+        }
+    }
+
+    fn path(&self, title: &str) -> Option<String> {
+        manager_path(&self.managers, title)
+    }
+
+    /// Whether an `[install]` entry was chosen with `-i`.
+    pub fn selected(&self, title: &str) -> bool {
+        let title = title.to_lowercase();
+        self.only.is_empty() || self.only.contains(&title) || title == "groups" || title == "protected"
+    }
+
     pub fn matches(&mut self, title: &str, installer: &Value) -> Result<(), Err> {
-        let title = title.to_string();
-        let array = get_array(&title, installer);
-        match title.to_lowercase().as_str() {
-            "paru" | "yay" | "pacman" => {
-                self.gather.push((title,Manager::Arch, array));
+        if title.eq_ignore_ascii_case("groups") {
+            return self.groups(installer)
+        }
+        if title.eq_ignore_ascii_case("protected") {
+            self.protected.extend(Package::names(&get_array(title, installer)));
+            return Ok(())
+        }
+        if let Some(Value::Boolean(false)) = installer.get("prune") {
+            self.keep.push(title.to_lowercase());
+        }
+        if let Some(after) = installer.get("after") {
+            let after = Package::names(&get_array("after", after)).iter().map(|title| title.to_lowercase()).collect();
+            self.after.push((title.to_lowercase(), after));
+        }
+        if title.eq_ignore_ascii_case("system") {
+            let packages = self.system_packages(title, installer);
+            self.system.extend(packages);
+            return Ok(())
+        }
+        let mut title = title.to_string();
+        let array = Self::packages(&title, installer);
+        let manager = self.manager(&title)?;
+        if manager == Manager::Vsc {
+            title = "code".to_string();
+        }
+        if let Some((_, _, existing)) = self.gather.iter_mut().find(|(existing, _, _)| existing.eq_ignore_ascii_case(&title)) {
+            for package in array {
+                if !existing.contains(&package) {
+                    existing.push(package)
+                }
+            }
+            return Ok(())
+        }
+        match manager {
+            Manager::Arch => {
                 self.arch+=1;
             },
-            "apt" => {
-                self.gather.push((title, Manager::Debian, array))
-            },
-            "zypper" => {
-                self.gather.push((title, Manager::OpenSUSE, array));
-            },
-            "dnf" => {
-                self.gather.push((title, Manager::Fedora, array));
-            },
-            "vsc" | "code" | "vscode" => {
-                self.gather.push(("code".to_string(),Manager::Vsc, array));
+            Manager::Vsc => {
                 self.vsc+=1
             },
-            "vscodium" => {
-                self.gather.push((title,Manager::Vscodium, array))
-            }
-            "flatpak" => {
-                self.gather.push((title,Manager::Flatpak, array))
-            }
-            &_ => {
-                Err(Err::InvalidPackage)?
-            }
+            _ => {}
         }
+        self.gather.push((title, manager, array));
         if self.arch > 1 || self.vsc > 1 {
             Err(Err::TooMany)?
         }
         Ok(())
     }
 
+    /// Packages of an `[install]` entry, written either as an array or as a
+    /// table with `packages` and per-manager settings such as `prune`.
+    pub fn packages(title: &str, installer: &Value) -> Vec<Value> {
+        match installer {
+            Value::Table(table) => table.get("packages").map(|p| get_array("packages", p)).unwrap_or_default(),
+            _ => get_array(title, installer)
+        }
+    }
+
+    pub fn system_packages(&self, title: &str, installer: &Value) -> Vec<Value> {
+        let (packages, map) = match installer {
+            Value::Table(table) => {
+                let packages = table.get("packages").map(|p| get_array("packages", p)).unwrap_or_default();
+                (packages, table.get("map").map(|m| get_table("map", m)))
+            },
+            _ => (get_array(title, installer), None)
+        };
+        let (Some(map), Some(distro)) = (map, &self.distro) else {
+            return packages
+        };
+        packages.into_iter().filter_map(|mut package| {
+            let name = Package::name(&package);
+            let renamed = distro.ids()
+                .find_map(|id| map.get(id).and_then(|names| get_table(id, names).get(&name).map(get_string)));
+            match (renamed, &mut package) {
+                (Some(renamed), _) if renamed.is_empty() => None,
+                (Some(renamed), Value::Table(table)) => {
+                    table.insert("name".to_string(), Value::String(renamed));
+                    Some(package)
+                },
+                (Some(renamed), _) => Some(Value::String(renamed)),
+                (None, _) => Some(package)
+            }
+        }).collect()
+    }
+
+    fn route_system(&mut self) {
+        if self.system.is_empty() {
+            return
+        }
+        let Some(title) = self.distro.as_ref().and_then(Distro::manager) else {
+            println!("{}","Could not detect the native package manager for [install.system].".red());
+            return
+        };
+        let manager = Manager::new(title).unwrap();
+        let packages = take(&mut self.system);
+        let title = match self.gather.iter_mut().find(|(_, m, _)| *m == manager) {
+            Some((existing_title, _, existing)) => {
+                for package in packages {
+                    if !existing.contains(&package) {
+                        existing.push(package)
+                    }
+                }
+                existing_title.to_lowercase()
+            },
+            None => {
+                self.gather.push((title.to_string(), manager, packages));
+                title.to_string()
+            }
+        };
+        if self.keep.iter().any(|keep| keep == "system") {
+            self.keep.push(title);
+        }
+    }
+
+    fn prog(&self, title: &str, manager: &Manager) -> Prog {
+        let mut prog = manager.prog(title);
+        if let Some(path) = self.path(title) {
+            let binary = prog.binary().to_string();
+            prog.locate(&binary, &path);
+        }
+        prog.protected.extend(self.protected.iter().cloned());
+        if self.keep.contains(&title.to_lowercase()) {
+            prog.removal = Removal::Never;
+        } else if self.no_remove {
+            prog.removal = Removal::Skip;
+        }
+        prog
+    }
+
+    /// Builds every gathered manager with its packages, native package
+    /// managers first, marking which ones are native.
+    pub fn progs(&mut self) -> Vec<(Prog, bool)> {
+        self.route_system();
+        let (system, rest): (Vec<_>, Vec<_>) = take(&mut self.gather).into_iter().partition(|(_, manager, _)| {
+            matches!(manager, Manager::Arch | Manager::Debian | Manager::OpenSUSE | Manager::Fedora | Manager::Void | Manager::Alpine | Manager::Gentoo)
+        });
+        let native = system.len();
+        system.iter().chain(&rest).enumerate().map(|(index, (title, manager, packages))| {
+            let mut prog = self.prog(title, manager);
+            prog.packages = packages.iter().map(Package::new).collect();
+            (prog, index < native)
+        }).collect()
+    }
+
+    /// Adds and removes `[repositories]`. Runs before any package is installed,
+    /// and on its own when the config has no `[install]`.
+    pub fn sync_repositories(&self) {
+        let db = PackDatabase::new();
+        db.conn.execute("BEGIN TRANSACTION", ()).unwrap();
+        db.create_table();
+        self.repositories.structure(&mut PackStatements::repositories(&db.conn));
+        db.conn.execute("COMMIT TRANSACTION", ()).unwrap();
+    }
+
     pub fn structure(&mut self) {
+        self.sync_repositories();
         let db = PackDatabase::new();
         db.conn.execute("BEGIN TRANSACTION", ()).unwrap();
         db.create_table();
         let mut statements = PackStatements::new(&db.conn);
-        self.gather.retain(|(title, _,packages)|{
-            match title.as_str() {
-                "paru" | "yay" | "pacman" => {
-                    Arch::new(title).prog.init(packages, &mut statements);
-                    false
-                },
-                "apt" => {
-                    Debian::new(&title).prog.init(packages, &mut statements);
-                    false
-                },
-                "zypper" => {
-                    OpenSUSE::new(&title).prog.init(packages, &mut statements);
-                    false
-                },
-                "dnf" => {
-                    Fedora::new(&title).prog.init(packages, &mut statements);
-                    false
-                },
-                &_ => {true}
+        let progs = self.progs();
+        let system_titles: Vec<String> = progs.iter().filter(|(_, native)| *native).map(|(prog, _)| prog.title.to_lowercase()).collect();
+        let progs: Vec<(Prog, Vec<String>)> = progs.into_iter().map(|(prog, native)| {
+            let title = prog.title.to_lowercase();
+            let mut after = self.after.iter().find(|(name, _)| *name == title)
+                .map(|(_, after)| after.clone()).unwrap_or_default();
+            if !prog.available() && !native {
+                after.extend(system_titles.iter().cloned());
             }
-        });
-        for (title,_,packages) in &self.gather {
-            match title.as_str() {
-                "code" => { Vsc::new(&title).prog.init(&packages, &mut statements) },
-                "flatpak" => { Flatpak::new(&title).prog.init(&packages, &mut statements) }
-                "vscodium" => { Vsc::new(&title).prog.init(&packages, &mut statements) }
-                &_ => {}
+            (prog, after)
+        }).collect();
+        if progs.iter().any(|(prog, _)| prog.prog == "sudo") {
+            Command::new("sudo").arg("-v").status().unwrap();
+        }
+        let mut outcomes = Vec::new();
+        let mut pending: Vec<&(Prog, Vec<String>)> = progs.iter().collect();
+        while !pending.is_empty() {
+            let titles: Vec<String> = pending.iter().map(|(prog, _)| prog.title.to_lowercase()).collect();
+            let (mut ready, waiting): (Vec<_>, Vec<_>) = pending.into_iter()
+                .partition(|(_, after)| after.iter().all(|title| !titles.contains(title)));
+            if ready.is_empty() {
+                println!("{}",format!("Managers depend on each other: {}, running them in order.",titles.join(", ")).red());
+                ready = waiting;
+                pending = Vec::new();
+            } else {
+                pending = waiting;
             }
+            thread::scope(|scope| {
+                let handles: Vec<_> = ready.iter().map(|(prog, _)| (prog, scope.spawn(|| prog.install_missing()))).collect();
+                for (prog, handle) in handles {
+                    outcomes.push((prog, handle.join().unwrap()));
+                }
+            });
+        }
+        let mut failures = Vec::new();
+        for (prog, (installed, failed)) in outcomes {
+            prog.track(&installed, &mut statements);
+            failures.extend(failed);
         }
+        let mut holds = PackStatements::holds(&db.conn);
+        for (prog, _) in &progs {
+            if prog.available() {
+                failures.extend(prog.unhold(&mut holds));
+                failures.extend(prog.uninstall(&mut statements));
+            } else {
+                // Dropped packages are offered for removal once the manager is available.
+                statements.zero.execute([&prog.title]).unwrap();
+            }
+        }
+        report(&failures);
         db.conn.execute("COMMIT TRANSACTION", ()).unwrap();
     }
 }
 
+/// The `path` a built in manager is found at, when `[managers.<name>]` sets one.
+pub fn manager_path(managers: &Map<String, Value>, title: &str) -> Option<String> {
+    let (name, definition) = managers.iter().find(|(name, _)| name.eq_ignore_ascii_case(title))?;
+    Custom::path(name, definition)
+}
+
+/// A command that did not succeed, reported once every manager has run.
+pub struct Failure {
+    pub title: String,
+    pub packages: Vec<String>,
+    pub message: String,
+    pub skipped: bool,
+}
+
+pub fn report(failures: &[Failure]) {
+    let (skipped, failures): (Vec<&Failure>, Vec<&Failure>) = failures.iter().partition(|failure| failure.skipped);
+    if !skipped.is_empty() {
+        println!("{}","Skipped:".yellow().bold().underline());
+        for skip in skipped {
+            println!("{} {}",format!("[{}]",skip.title).cyan(),skip.message.yellow());
+        }
+    }
+    if failures.is_empty() {
+        return
+    }
+    println!("{}","Failed:".red().bold().underline());
+    for failure in failures {
+        println!("{} {}",format!("[{}]",failure.title).cyan(),failure.packages.join(", ").red());
+        for line in failure.message.lines() {
+            println!("    {line}")
+        }
+    }
+    if let Some(log) = log_path() {
+        println!("Full output written to {}",log.display().to_string().yellow());
+    }
+}
+
 impl Prog {
-    fn init(&mut self, packages: &Vec<Value>, statements: &mut PackStatements) {
-        let bin = PathBuf::from(format!("/usr/bin/{}",self.prog));
-        if bin.exists() {
-            let installed = format!("{:?}",checker(&self.prog, &self.checker));
-            self.packages = convert_to_string(packages);
-            let mut to_install = Vec::new();
-            for package in &self.packages {
-                if !self.installed(&installed, package) {
-                    if self.prog == "code" || self.prog == "vscodium" {
-                        self.install_command(&vec![package.to_string()]);
-                    } else {
-                        to_install.push(package.to_string());
+    /// Keeps tracked packages that are still listed, and only starts
+    /// tracking new ones once they are confirmed to be installed.
+    fn track(&self, installed: &[String], statements: &mut PackStatements) {
+        for package in &self.packages {
+            if statements.update.execute((&package.name, &self.title)).unwrap() == 0
+                && self.installed(installed, &self.naming.installed(&package.name)) {
+                statements.insert.execute((&package.name, &self.title)).unwrap();
+            }
+        }
+    }
+
+    fn failure(&self, packages: &[String], message: String) -> Failure {
+        Failure {
+            title: self.title.to_string(),
+            packages: packages.to_vec(),
+            message,
+            skipped: false
+        }
+    }
+
+    fn skipped(&self, packages: &[String], reason: String) -> Failure {
+        Failure {
+            skipped: true,
+            ..self.failure(packages, reason)
+        }
+    }
+
+    /// The program that has to exist for this manager to be usable.
+    pub fn binary(&self) -> &str {
+        match self.prog.as_str() {
+            "sudo" => &self.install[0],
+            prog => prog
+        }
+    }
+
+    /// Why this manager cannot run, checking sudo as well as the manager
+    /// itself when it is escalated.
+    pub fn missing(&self) -> Option<String> {
+        missing_program(&self.prog).or_else(|| missing_program(self.binary()))
+    }
+
+    fn available(&self) -> bool {
+        self.missing().is_none()
+    }
+
+    /// Installs every listed package that is missing, and returns the
+    /// packages installed afterwards along with anything that failed.
+    fn install_missing(&self) -> (Vec<String>, Vec<Failure>) {
+        let names: Vec<String> = self.packages.iter().map(|package| package.name.to_string()).collect();
+        if let Some(reason) = self.missing() {
+            println!("{} {}",self.prefix(),format!("Skipped: {reason}").yellow());
+            return (Vec::new(), vec![self.skipped(&names, reason)])
+        }
+        let installed = self.checker.run();
+        let mut failures = Vec::new();
+        let mut to_install = Vec::new();
+        let mut attempted = false;
+        for package in &self.packages {
+            if !self.installed(&installed, &self.naming.installed(&package.name)) {
+                attempted = true;
+                if !self.batch || !package.plain() {
+                    if let Err(message) = self.install_package(package, &[]) {
+                        failures.push(self.failure(&[package.name.to_string()], message));
                     }
+                } else {
+                    to_install.push(package.name.to_string());
                 }
-                if statements.update.execute((package, &self.prog)).unwrap() == 0 {
-                    statements.insert.execute((package, &self.prog)).unwrap();
-                }
             }
-            if !to_install.is_empty() {
-                self.install_command(&to_install);
+        }
+        for package in self.drifted(&installed) {
+            println!("{} {}",self.prefix(),format!("{} does not match version {}, reinstalling.",package.name,package.version.as_deref().unwrap_or_default()).yellow());
+            if let Err(message) = self.install_package(package, &self.repin) {
+                failures.push(self.failure(&[package.name.to_string()], message));
+            }
+        }
+        if !to_install.is_empty() {
+            if let Err(message) = self.install_command(&to_install) {
+                failures.push(self.failure(&to_install, message));
             }
-            self.uninstall(statements);
         }
-        else {
-            println!("{}",format!("{} is not installed on your system.",self.prog).red())
+        let installed = match attempted {
+            true => self.checker.run(),
+            false => installed
+        };
+        for failure in &mut failures {
+            failure.packages.retain(|package| !self.installed(&installed, &self.naming.installed(package)));
+        }
+        failures.retain(|failure| !failure.packages.is_empty());
+        if let Err(failure) = self.hold() {
+            failures.push(failure);
         }
+        (installed, failures)
+    }
+
+    fn prefix(&self) -> ColoredString {
+        format!("[{}]",self.title).cyan()
     }
 
-    fn uninstall(&self, statements: &mut PackStatements) {
-        let pack_iter = statements.select.query_map([&self.prog],|row|{
+    fn run(&self, manager: &str, args: &[String], packages: &[String]) -> Result<(), String> {
+        get_prefixed_buffer(&self.prefix(), manager, args, packages)
+    }
+
+    /// Repeats the last argument before every package for managers such as
+    /// `code --install-extension a --install-extension b`.
+    fn per_package(&self, args: &[String], packages: &[String]) -> (Vec<String>, Vec<String>) {
+        match (self.repeat, args.split_last()) {
+            (true, Some((flag, args))) => (args.to_vec(), packages.iter().flat_map(|package| [flag.to_string(), package.to_string()]).collect()),
+            _ => (args.to_vec(), packages.to_vec())
+        }
+    }
+
+    fn uninstall(&self, statements: &mut PackStatements) -> Vec<Failure> {
+        let dropped: Vec<String> = statements.select.query_map([&self.title],|row|{
             let value:String = row.get(0)?;
             Ok(value)
-        }).unwrap();
-        let mut to_uninstall = Vec::new();
-        for package in pack_iter {
-            if self.prog == "code" || self.prog == "vscodium" {
-                self.uninstall_command(&vec![package.unwrap()]);
-            } else {
-                to_uninstall.push(package.unwrap())
+        }).unwrap().map(Result::unwrap).collect();
+        let (protected, dropped): (Vec<String>, Vec<String>) = dropped.into_iter()
+            .partition(|package| self.installed(&self.protected, package));
+        if !protected.is_empty() {
+            println!("{}",format!("Not removing protected {} packages: {}",self.title,protected.join(", ")).yellow());
+        }
+        let mut forget = protected;
+        let mut failures = Vec::new();
+        if !dropped.is_empty() {
+            match self.removal {
+                Removal::Never => {
+                    println!("{}",format!("{} has prune = false, no longer tracking: {}",self.title,dropped.join(", ")).yellow());
+                    forget.extend(dropped);
+                },
+                Removal::Skip => {
+                    println!("{}",format!("Skipping removal of {} packages: {}",self.title,dropped.join(", ")).yellow());
+                },
+                Removal::Prompt => {
+                    println!("{}",format!("{} packages no longer in your config:",self.title).bold().underline());
+                    for package in &dropped {
+                        println!("{package}")
+                    }
+                    if confirm(&format!("Remove {} packages?",dropped.len())) {
+                        let batches: Vec<Vec<String>> = match self.batch {
+                            true => vec![dropped],
+                            false => dropped.into_iter().map(|package| vec![package]).collect()
+                        };
+                        for batch in batches {
+                            match self.uninstall_command(&batch) {
+                                Ok(()) => forget.extend(batch),
+                                Err(message) => failures.push(self.failure(&batch, message))
+                            }
+                        }
+                    }
+                }
             }
-            
         }
-        if !to_uninstall.is_empty() {
-            self.uninstall_command(&to_uninstall);
+        for package in forget {
+            statements.delete.execute((package, &self.title)).unwrap();
         }
-        statements.remove.execute([&self.prog]).unwrap();
-        statements.zero.execute([&self.prog]).unwrap();
+        statements.zero.execute([&self.title]).unwrap();
+        failures
     }
 
-    fn install_command(&self, prog: &Vec<String>) {
-        get_buffer(&self.prog, &self.install, prog)
+    pub fn upgrade_all(&self) -> Vec<Failure> {
+        if let Some(reason) = self.missing() {
+            return vec![self.skipped(&[], reason)]
+        }
+        let mut failures = self.run_all(&self.upgrade);
+        if self.reinstall {
+            let (plain, pinned): (Vec<&Package>, Vec<&Package>) = self.packages.iter().partition(|package| self.batch && package.plain());
+            for package in pinned {
+                if let Err(message) = self.install_package(package, &[]) {
+                    failures.push(self.failure(&[package.name.to_string()], message));
+                }
+            }
+            let plain: Vec<String> = plain.iter().map(|package| package.name.to_string()).collect();
+            if !plain.is_empty() {
+                if let Err(message) = self.install_command(&plain) {
+                    failures.push(self.failure(&plain, message));
+                }
+            }
+        }
+        failures
     }
 
-    fn uninstall_command(&self, prog: &Vec<String>) {
-        get_buffer(&self.prog, &self.uninstall, prog)
+    pub fn clean_all(&self) -> Vec<Failure> {
+        if let Some(reason) = self.missing() {
+            return vec![self.skipped(&[], reason)]
+        }
+        let mut failures = self.run_all(&self.clean);
+        let Some(orphans) = &self.orphans else {
+            return failures
+        };
+        let orphans: Vec<String> = orphans.run().into_iter().filter(|package| !self.installed(&self.protected, package)).collect();
+        if orphans.is_empty() {
+            return failures
+        }
+        println!("{}",format!("{} packages that are no longer needed:",self.title).bold().underline());
+        for package in &orphans {
+            println!("{package}")
+        }
+        if confirm(&format!("Remove {} packages?",orphans.len())) {
+            if let Err(message) = self.uninstall_command(&orphans) {
+                failures.push(self.failure(&orphans, message));
+            }
+        }
+        failures
+    }
+
+    fn run_all(&self, commands: &[Vec<String>]) -> Vec<Failure> {
+        commands.iter().filter_map(|command| {
+            self.run(&command[0], &command[1..], &[]).err().map(|message| self.failure(&[command.join(" ")], message))
+        }).collect()
+    }
+
+    fn install_command(&self, prog: &[String]) -> Result<(), String> {
+        let prog: Vec<String> = prog.iter().map(|package| self.naming.install(package)).collect();
+        let (args, prog) = self.per_package(&self.install, &prog);
+        self.run(&self.prog, &args, &prog)
+    }
+
+    /// Pinned packages that are installed at a version other than their pin.
+    fn drifted(&self, installed: &[String]) -> Vec<&Package> {
+        let Some(versions) = self.versions.as_ref().filter(|_| self.packages.iter().any(|package| package.version.is_some())) else {
+            return Vec::new()
+        };
+        let versions = versions.versions();
+        self.packages.iter()
+            .filter(|package| self.installed(installed, &self.naming.installed(&package.name)))
+            .filter(|package| package.version.as_ref().is_some_and(|pin| {
+                versions.iter().any(|(name, _)| name == &package.name)
+                    && !versions.iter().any(|(name, version)| name == &package.name && Version::matches(pin, version))
+            }))
+            .collect()
     }
 
-    fn installed(&self, installed: &str, mtch: &str) -> bool {
-        let reg = "\\\"|\\\\n|\\\\t| |/|.";
-        let reg = &format!("({}|^){}({}|$)",&reg,&mtch,&reg);
-        let re = Regex::new(reg).unwrap();
-        return re.is_match(installed);
+    /// `extra` is passed along when replacing an installed version.
+    fn install_package(&self, package: &Package, extra: &[String]) -> Result<(), String> {
+        if package.version.is_some() && self.version == Version::Unsupported {
+            println!("{} {}",self.prefix(),format!("{} does not support pinning versions, installing the latest {}.",self.title,package.name).yellow())
+        }
+        let args: Vec<String> = self.install.iter().chain(extra).chain(&package.options).cloned().collect();
+        self.run(&self.prog, &args, &self.version.args(&self.naming, package))
     }
-}
\ No newline at end of file
+
+    fn hold(&self) -> Result<(), Failure> {
+        let held: Vec<String> = self.packages.iter().filter(|package| package.hold).map(|package| package.name.to_string()).collect();
+        if held.is_empty() {
+            return Ok(())
+        }
+        match &self.hold {
+            Hold::Command(args, _) => self.run(&self.prog, args, &held).map_err(|message| self.failure(&held, message)),
+            Hold::IgnorePkg => {
+                let conf = fs::read_to_string("/etc/pacman.conf").unwrap_or_default();
+                let ignored: Vec<&str> = conf.lines()
+                    .filter_map(|line| line.trim().strip_prefix("IgnorePkg"))
+                    .filter_map(|line| line.trim_start().strip_prefix('='))
+                    .flat_map(str::split_whitespace)
+                    .collect();
+                for package in held.iter().filter(|package| !ignored.contains(&package.as_str())) {
+                    self.run("sudo", &["sed".to_string(), "-i".to_string(), format!("/^\\[options\\]/a IgnorePkg = {package}")], &["/etc/pacman.conf".to_string()])
+                        .map_err(|message| self.failure(&[package.to_string()], message))?
+                }
+                Ok(())
+            },
+            Hold::Unsupported => {
+                println!("{} {}",self.prefix(),format!("{} does not support holding packages: {}",self.title,held.join(", ")).yellow());
+                Ok(())
+            }
+        }
+    }
+
+    /// Tracks held packages and releases the ones that are no longer held
+    /// in the config.
+    fn unhold(&self, statements: &mut PackStatements) -> Vec<Failure> {
+        for package in self.packages.iter().filter(|package| package.hold) {
+            if statements.update.execute((&package.name, &self.title)).unwrap() == 0 {
+                statements.insert.execute((&package.name, &self.title)).unwrap();
+            }
+        }
+        let released: Vec<String> = statements.select.query_map([&self.title],|row|{
+            let value:String = row.get(0)?;
+            Ok(value)
+        }).unwrap().map(Result::unwrap).collect();
+        statements.remove.execute([&self.title]).unwrap();
+        statements.zero.execute([&self.title]).unwrap();
+        if released.is_empty() {
+            return Vec::new()
+        }
+        println!("{} {}",self.prefix(),format!("Releasing held packages: {}",released.join(", ")).yellow());
+        match &self.hold {
+            Hold::Command(_, args) => self.run(&self.prog, args, &released).err().map(|message| self.failure(&released, message)).into_iter().collect(),
+            Hold::IgnorePkg => released.iter().filter_map(|package| {
+                self.run("sudo", &["sed".to_string(), "-i".to_string(), format!("/^IgnorePkg = {package}$/d")], &["/etc/pacman.conf".to_string()])
+                    .err().map(|message| self.failure(&[package.to_string()], message))
+            }).collect(),
+            Hold::Unsupported => Vec::new()
+        }
+    }
+
+    fn uninstall_command(&self, prog: &[String]) -> Result<(), String> {
+        let prog: Vec<String> = prog.iter().map(|package| self.naming.uninstall(package)).collect();
+        let (args, prog) = self.per_package(&self.uninstall, &prog);
+        match (&self.naming, &self.uninstaller) {
+            (Naming::GoModule(_), _) => self.run("rm", &args, &prog),
+            (_, Some(uninstaller)) => self.run(uninstaller, &args, &prog),
+            _ => self.run(&self.prog, &args, &prog)
+        }
+    }
+
+    fn installed(&self, installed: &[String], package: &str) -> bool {
+        installed.iter().any(|name| name == package || self.ignore_case && name.eq_ignore_ascii_case(package))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::{Install, Manager};
+    use crate::manage_data::tools::missing_program;
+    use crate::installation::{custom::Custom, package::Package, installers::{Alpine, Arch, Brew, Builder, Cargo, Debian, Fedora, Flatpak, Gem, Gentoo, Go, Nix, Npm, OpenSUSE, Pipx, Prog, Snap, Version, Void, Vsc}};
+
+    const PACMAN: &str = "gcc\ngcc-libs\nlib32-glibc\nvim\nvim-runtime\n";
+    const DPKG: &str = "installed g++-12\ninstalled libfoo.so.1\nconfig-files neovim\ninstalled vim-runtime\ninstalled vim\n";
+    const RPM: &str = "gcc-c++\nvim-enhanced\nvim-common\nlibfoo.so\n";
+    const FLATPAK: &str = "com.sindresorhus.Caprine\norg.freedesktop.Platform\n";
+    const CODE: &str = "GitHub.copilot\nesbenp.prettier-vscode\n";
+    const CARGO: &str = "cargo-edit v0.12.2:\n    cargo-add\n    cargo-rm\nripgrep v14.1.0:\n    rg\n";
+    const PIPX: &str = "black 24.4.2\npoetry 1.8.3\n";
+    const NPM: &str = "/usr/lib\n/usr/lib/node_modules/npm\n/usr/lib/node_modules/@vue/cli\n/usr/lib/node_modules/typescript\n";
+    const GEM: &str = "\n*** LOCAL GEMS ***\n\nbundler\nrake\n";
+    const XBPS: &str = "ii base-system-0.114_1    Void Linux base system meta package\nii gcc-13.2.0_1    GNU Compiler Collection\nii xbps-0.59.2_1    XBPS package system utilities\n";
+    const XBPS_MANUAL: &str = "base-system-0.114_1\nxbps-0.59.2_1\n";
+    const APK_WORLD: &str = "alpine-base\nvim<9.2\nopenssh>=9.0\ngit\n";
+    const PORTAGE: &str = "/var/db/pkg/sys-devel/gcc-13.2.1_p20240210\n/var/db/pkg/app-editors/vim-9.1.0-r1\n/var/db/pkg/dev-lang/python-3.12.3\n";
+    const NIX: &str = "Name:               ripgrep\nFlake attribute:    legacyPackages.x86_64-linux.ripgrep\nOriginal flake URL: flake:nixpkgs\n\nName:               hello\n";
+    const SNAP: &str = "Name      Version   Rev    Tracking       Publisher   Notes\ncore22    20240408  1380   latest/stable  canonical✓  base\nfirefox   125.0.3   4173   latest/stable  mozilla✓    -\n";
+    const ZYPPER: &str = "S  | Repository | Name | Version | Arch\n---+------------+------+---------+-------\ni+ | repo-oss   | vim  | 9.1.0   | x86_64\ni  | repo-oss   | gcc  | 13-1.2  | x86_64\n";
+
+    #[test]
+    fn pacman_matches_exact_names() {
+        let prog = Arch::new("pacman").prog;
+        let installed = prog.checker.output.parse(PACMAN);
+        assert!(prog.installed(&installed, "vim"));
+        assert!(prog.installed(&installed, "gcc"));
+        assert!(!prog.installed(&installed, "vi"));
+        assert!(!prog.installed(&installed, "glibc"));
+        assert!(!prog.installed(&installed, "gcc-lib"));
+    }
+
+    #[test]
+    fn dpkg_skips_packages_that_are_not_installed() {
+        let prog = Debian::new("apt").prog;
+        let installed = prog.checker.output.parse(DPKG);
+        assert_eq!(installed, ["g++-12", "libfoo.so.1", "vim-runtime", "vim"]);
+        assert!(prog.installed(&installed, "vim"));
+        assert!(!prog.installed(&installed, "neovim"));
+        assert!(!prog.installed(&installed, "g++"));
+        assert!(!prog.installed(&installed, "libfoo.so"));
+    }
+
+    #[test]
+    fn rpm_handles_special_characters() {
+        for prog in [OpenSUSE::new("zypper").prog, Fedora::new("dnf").prog] {
+            let installed = prog.checker.output.parse(RPM);
+            assert!(prog.installed(&installed, "gcc-c++"));
+            assert!(prog.installed(&installed, "libfoo.so"));
+            assert!(!prog.installed(&installed, "gcc"));
+            assert!(!prog.installed(&installed, "vim"));
+        }
+    }
+
+    #[test]
+    fn flatpak_matches_application_ids() {
+        let prog = Flatpak::new("flatpak").prog;
+        let installed = prog.checker.output.parse(FLATPAK);
+        assert!(prog.installed(&installed, "com.sindresorhus.Caprine"));
+        assert!(!prog.installed(&installed, "com.sindresorhus"));
+        assert!(!prog.installed(&installed, "com.sindresorhus.caprine"));
+    }
+
+    #[test]
+    fn code_extensions_ignore_case() {
+        let prog = Vsc::new("code").prog;
+        let installed = prog.checker.output.parse(CODE);
+        assert!(prog.installed(&installed, "github.copilot"));
+        assert!(prog.installed(&installed, "esbenp.prettier-vscode"));
+        assert!(!prog.installed(&installed, "github.copilot-chat"));
+        let packages = ["github.copilot".to_string(), "esbenp.prettier-vscode".to_string()];
+        let (args, packages) = prog.per_package(&prog.install, &packages);
+        assert!(args.is_empty());
+        assert_eq!(packages, ["--install-extension", "github.copilot", "--install-extension", "esbenp.prettier-vscode"]);
+    }
+
+    #[test]
+    fn zypper_lists_user_installed_names() {
+        let prog = OpenSUSE::new("zypper").prog;
+        assert_eq!(prog.explicit.output.parse(ZYPPER), ["vim", "gcc"]);
+    }
+
+    #[test]
+    fn language_managers_list_names() {
+        assert_eq!(Cargo::new("cargo").prog.checker.output.parse(CARGO), ["cargo-edit", "ripgrep"]);
+        assert_eq!(Pipx::new("pipx").prog.checker.output.parse(PIPX), ["black", "poetry"]);
+        assert_eq!(Npm::new("npm").prog.checker.output.parse(NPM), ["npm", "@vue/cli", "typescript"]);
+        assert_eq!(Gem::new("gem").prog.checker.output.parse(GEM), ["bundler", "rake"]);
+    }
+
+    #[test]
+    fn go_modules_map_to_binaries() {
+        let prog = Go::new("go").prog;
+        assert_eq!(prog.naming.installed("golang.org/x/tools/gopls@latest"), "gopls");
+        assert_eq!(prog.naming.installed("github.com/go-delve/delve/cmd/dlv"), "dlv");
+        assert_eq!(prog.naming.installed("github.com/golangci/golangci-lint/v2/cmd/golangci-lint@v2.1.0"), "golangci-lint");
+        assert_eq!(prog.naming.installed("example.com/tool/v3"), "tool");
+        assert_eq!(prog.naming.install("golang.org/x/tools/gopls"), "golang.org/x/tools/gopls@latest");
+        assert_eq!(prog.naming.install("golang.org/x/tools/gopls@v0.16.0"), "golang.org/x/tools/gopls@v0.16.0");
+    }
+
+    #[test]
+    fn system_managers_strip_versions() {
+        let prog = Void::new("xbps").prog;
+        let installed = prog.checker.output.parse(XBPS);
+        assert_eq!(installed, ["base-system", "gcc", "xbps"]);
+        assert_eq!(prog.explicit.output.parse(XBPS_MANUAL), ["base-system", "xbps"]);
+        assert_eq!(Alpine::new("apk").prog.explicit.output.parse(APK_WORLD), ["alpine-base", "vim", "openssh", "git"]);
+        assert_eq!(Gentoo::new("emerge").prog.checker.output.parse(PORTAGE), ["sys-devel/gcc", "app-editors/vim", "dev-lang/python"]);
+    }
+
+    #[test]
+    fn nix_and_snap_list_names() {
+        let prog = Nix::new("nix").prog;
+        let installed = prog.checker.output.parse(NIX);
+        assert_eq!(installed, ["ripgrep", "hello"]);
+        assert!(prog.installed(&installed, &prog.naming.installed("nixpkgs#ripgrep")));
+        assert_eq!(prog.naming.installed("github:nix-community/home-manager#packages.x86_64-linux.home-manager"), "home-manager");
+        assert_eq!(prog.naming.install("ripgrep"), "nixpkgs#ripgrep");
+        assert_eq!(Snap::new("snap").prog.checker.output.parse(SNAP), ["core22", "firefox"]);
+    }
+
+    #[test]
+    fn custom_managers_build_from_toml() {
+        let definition: toml::Value = toml::from_str(r#"
+            install = "opkg install"
+            uninstall = ["opkg", "remove"]
+            list = "opkg list-installed"
+            escalate = true
+            batch = false
+            parser = "regex"
+            regex = '^(\S+) - '
+        "#).unwrap();
+        let prog = Custom::new("opkg", &definition).prog("opkg");
+        assert_eq!(prog.prog, "sudo");
+        assert_eq!(prog.title, "opkg");
+        assert_eq!(prog.install, ["opkg", "install"]);
+        assert_eq!(prog.uninstall, ["opkg", "remove"]);
+        assert!(!prog.batch);
+        assert_eq!(prog.checker.command, ["opkg", "list-installed"]);
+        assert_eq!(prog.checker.output.parse("busybox - 1.36.1-1\nzlib - 1.3-1\n"), ["busybox", "zlib"]);
+        assert!(prog.uninstaller.is_none());
+        let definition: toml::Value = toml::from_str(r#"
+            install = "foo add"
+            uninstall = "bar rm"
+            list = "foo list"
+        "#).unwrap();
+        let prog = Custom::new("foo", &definition).prog("foo");
+        assert_eq!(prog.prog, "foo");
+        assert_eq!(prog.install, ["add"]);
+        assert_eq!(prog.uninstaller.as_deref(), Some("bar"));
+        assert_eq!(prog.uninstall, ["rm"]);
+    }
+
+    #[test]
+    fn pinned_versions_follow_the_manager() {
+        let packages: toml::Value = toml::from_str(r#"
+            packages = ["git", { name = "nodejs", version = "20.*", options = ["--no-install-recommends"], hold = true }]
+        "#).unwrap();
+        let packages: Vec<Package> = packages["packages"].as_array().unwrap().iter().map(Package::new).collect();
+        assert!(packages[0].plain());
+        assert!(!packages[1].plain() && packages[1].hold);
+        assert_eq!(packages[1].options, ["--no-install-recommends"]);
+        let args = |prog: Prog| prog.version.args(&prog.naming, &packages[1]);
+        assert_eq!(args(Debian::new("apt").prog), ["nodejs=20.*"]);
+        assert_eq!(args(Fedora::new("dnf").prog), ["nodejs-20.*"]);
+        assert_eq!(args(Cargo::new("cargo").prog), ["--version", "20.*", "nodejs"]);
+        assert_eq!(args(Pipx::new("pipx").prog), ["nodejs==20.*"]);
+        assert_eq!(args(Go::new("go").prog), ["nodejs@20.*"]);
+        assert_eq!(args(Arch::new("pacman").prog), ["nodejs"]);
+    }
+
+    #[test]
+    fn installed_versions_are_checked_against_pins() {
+        let versions = |prog: Prog, output: &str| prog.versions.unwrap().output.versions(output);
+        assert_eq!(versions(Debian::new("apt").prog, "installed nodejs 20.11.1-1nodesource1\nconfig-files vim 2:9.0\n"), [("nodejs".to_string(), "20.11.1-1nodesource1".to_string())]);
+        assert_eq!(versions(Cargo::new("cargo").prog, CARGO), [("cargo-edit".to_string(), "0.12.2".to_string()), ("ripgrep".to_string(), "14.1.0".to_string())]);
+        assert_eq!(versions(Npm::new("npm").prog, "/usr/lib\n├── @vue/cli@5.0.8\n└── typescript@5.4.5\n"), [("@vue/cli".to_string(), "5.0.8".to_string()), ("typescript".to_string(), "5.4.5".to_string())]);
+        assert!(Version::matches("20.*", "20.11.1-1nodesource1"));
+        assert!(Version::matches("20.11.1", "20.11.1-1.fc40"));
+        assert!(Version::matches("14.1.0", "14.1.0"));
+        assert!(!Version::matches("20.*", "21.6.0-1"));
+        assert!(!Version::matches("1.2", "1.20"));
+    }
+
+    #[test]
+    fn orphans_are_listed_before_removal() {
+        let orphans = |prog: Prog, output: &str| prog.orphans.unwrap().output.parse(output);
+        assert_eq!(orphans(Debian::new("apt").prog, "Reading package lists...\nRemv libfoo1 [1.2-3]\nRemv g++-12 [12.2.0-14]\n"), ["libfoo1", "g++-12"]);
+        assert_eq!(orphans(Void::new("xbps").prog, "libfoo-1.2_1\ngcc-13.2.0_1\n"), ["libfoo", "gcc"]);
+        assert_eq!(orphans(Brew::new("brew").prog, "==> Would autoremove 2 unneeded formulae:\nlibyaml\nopenssl@1.1\n"), ["libyaml", "openssl@1.1"]);
+        assert!(Gentoo::new("emerge").prog.clean.is_empty());
+    }
+
+    #[test]
+    fn managers_can_live_outside_of_path() {
+        let managers: toml::Value = toml::from_str(r#"
+            [apt]
+            path = "/opt/apt/bin/apt"
+        "#).unwrap();
+        let mut install = Install::new();
+        install.managers(Some(&managers));
+        let manager = install.manager("apt").ok().unwrap();
+        assert!(manager == Manager::Debian);
+        let prog = install.prog("apt", &manager);
+        assert_eq!(prog.prog, "sudo");
+        assert_eq!(prog.install[0], "/opt/apt/bin/apt");
+        assert_eq!(prog.uninstall[0], "/opt/apt/bin/apt");
+        assert_eq!(prog.upgrade[0], ["sudo", "/opt/apt/bin/apt", "update"]);
+        assert_eq!(prog.upgrade[1], ["sudo", "/opt/apt/bin/apt", "-y", "upgrade"]);
+        assert_eq!(prog.clean, [["sudo", "/opt/apt/bin/apt", "clean"]]);
+        assert_eq!(prog.orphans.as_ref().unwrap().command[0], "/opt/apt/bin/apt");
+        assert_eq!(prog.binary(), "/opt/apt/bin/apt");
+        assert_eq!(missing_program(prog.binary()).unwrap(), "/opt/apt/bin/apt does not exist or is not executable.");
+        install.repositories(None);
+        assert_eq!(install.repositories.program("apt"), "/opt/apt/bin/apt");
+        assert_eq!(install.repositories.program("flatpak"), "flatpak");
+        assert_eq!(Cargo::new("a-tool-that-does-not-exist").prog.missing().unwrap(), "a-tool-that-does-not-exist was not found in PATH.");
+        assert!(missing_program("sh").is_none());
+    }
+}
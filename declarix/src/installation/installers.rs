@@ -14,24 +14,273 @@ GNU General Public License for more details.
 You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
+use std::{env, path::PathBuf};
+use dirs::home_dir;
+use regex::Regex;
+
+use crate::manage_data::tools::checker;
+
+use super::package::Package;
+
 pub struct Prog {
     pub prog: String,
+    pub title: String,
     pub install: Vec<String>,
     pub uninstall: Vec<String>,
-    pub checker: Vec<String>,
-    pub packages: Vec<String>,
+    /// Runs `uninstall` instead of `prog`, when removing uses another tool.
+    pub uninstaller: Option<String>,
+    pub checker: Query,
+    pub explicit: Query,
+    pub ignore_case: bool,
+    pub batch: bool,
+    pub repeat: bool,
+    pub naming: Naming,
+    pub version: Version,
+    pub versions: Option<Query>,
+    pub repin: Vec<String>,
+    pub hold: Hold,
+    pub removal: Removal,
+    pub protected: Vec<String>,
+    pub upgrade: Vec<Vec<String>>,
+    pub reinstall: bool,
+    pub clean: Vec<Vec<String>>,
+    pub orphans: Option<Query>,
+    pub packages: Vec<Package>,
 }
 
 impl Prog {
-    fn new<'a>(prog: &'a str, install: impl IntoIterator<Item = &'a str>, uninstall: impl IntoIterator<Item = &'a str>, checker: impl IntoIterator<Item = &'a str>) -> Self {
+    pub fn new<'a>(prog: &'a str, title: &'a str, install: impl IntoIterator<Item = &'a str>, uninstall: impl IntoIterator<Item = &'a str>, checker: Query, explicit: Query) -> Self {
         Self {
             prog: prog.to_string(),
+            title: title.to_string(),
             install: install.into_iter().map(String::from).collect(),
             uninstall: uninstall.into_iter().map(String::from).collect(),
-            checker: checker.into_iter().map(String::from).collect(),
+            uninstaller: None,
+            checker,
+            explicit,
+            ignore_case: false,
+            batch: true,
+            repeat: false,
+            naming: Naming::Package,
+            version: Version::Unsupported,
+            versions: None,
+            repin: Vec::new(),
+            hold: Hold::Unsupported,
+            removal: Removal::Prompt,
+            protected: Vec::new(),
+            upgrade: Vec::new(),
+            reinstall: false,
+            clean: Vec::new(),
+            orphans: None,
             packages: Vec::new()
         }
     }
+
+    /// Runs `path` wherever a command would run `binary`, for tools that live
+    /// outside of `PATH`.
+    pub fn locate(&mut self, binary: &str, path: &str) {
+        let replace = |command: &mut Vec<String>| {
+            // Escalated upgrade and clean commands name the manager after sudo.
+            let start = usize::from(command.first().is_some_and(|first| first == "sudo"));
+            if let Some(arg) = command.get_mut(start).filter(|arg| *arg == binary) {
+                *arg = path.to_string();
+            }
+        };
+        for prog in [Some(&mut self.prog), self.uninstaller.as_mut()].into_iter().flatten() {
+            if prog == binary {
+                *prog = path.to_string();
+            }
+        }
+        for command in [&mut self.install, &mut self.uninstall, &mut self.checker.command, &mut self.explicit.command] {
+            replace(command);
+        }
+        let queries = self.orphans.iter_mut().chain(self.versions.iter_mut()).map(|query| &mut query.command);
+        for command in self.upgrade.iter_mut().chain(self.clean.iter_mut()).chain(queries) {
+            replace(command);
+        }
+        if let Hold::Command(hold, unhold) = &mut self.hold {
+            replace(hold);
+            replace(unhold);
+        }
+    }
+}
+
+pub struct Query {
+    pub command: Vec<String>,
+    pub output: Output,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Output {
+    Lines,
+    FirstColumn,
+    Regex(String),
+}
+
+/// How a pinned version is passed to the install command.
+#[derive(Clone, PartialEq)]
+pub enum Version {
+    Unsupported,
+    Suffix(String),
+    Flag(String),
+}
+
+/// How a package is held back from upgrades, and released again.
+pub enum Hold {
+    Unsupported,
+    Command(Vec<String>, Vec<String>),
+    IgnorePkg,
+}
+
+/// What happens to packages that were dropped from the config.
+#[derive(PartialEq)]
+pub enum Removal {
+    Prompt,
+    Skip,
+    Never,
+}
+
+pub enum Naming {
+    Package,
+    GoModule(PathBuf),
+    Flake,
+}
+
+impl Query {
+    pub fn new<'a>(command: impl IntoIterator<Item = &'a str>, output: Output) -> Self {
+        Self {
+            command: command.into_iter().map(String::from).collect(),
+            output
+        }
+    }
+
+    pub fn prog(&self) -> &str {
+        &self.command[0]
+    }
+
+    pub fn run(&self) -> Vec<String> {
+        self.output.parse(&checker(self.prog(), &self.command[1..].to_vec()))
+    }
+
+    pub fn versions(&self) -> Vec<(String, String)> {
+        self.output.versions(&checker(self.prog(), &self.command[1..].to_vec()))
+    }
+}
+
+impl Output {
+    pub fn parse(&self, output: &str) -> Vec<String> {
+        match self {
+            Self::Lines => {
+                output.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect()
+            },
+            Self::FirstColumn => {
+                output.lines().filter_map(|line| line.split_whitespace().next()).map(String::from).collect()
+            },
+            Self::Regex(reg) => {
+                let re = Regex::new(reg).unwrap();
+                output.lines().filter_map(|line| re.captures(line))
+                    .filter_map(|cap| cap.get(1))
+                    .map(|name| name.as_str().to_string())
+                    .collect()
+            }
+        }
+    }
+
+    /// Names along with their installed version, taken from the first two
+    /// columns or the first two groups of the regex.
+    pub fn versions(&self, output: &str) -> Vec<(String, String)> {
+        match self {
+            Self::Lines => Vec::new(),
+            Self::FirstColumn => {
+                output.lines().filter_map(|line| {
+                    let mut columns = line.split_whitespace();
+                    Some((columns.next()?.to_string(), columns.next()?.to_string()))
+                }).collect()
+            },
+            Self::Regex(reg) => {
+                let re = Regex::new(reg).unwrap();
+                output.lines().filter_map(|line| re.captures(line))
+                    .filter_map(|cap| Some((cap.get(1)?.as_str().to_string(), cap.get(2)?.as_str().to_string())))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Naming {
+    pub fn installed(&self, package: &str) -> String {
+        match self {
+            Self::Package => package.to_string(),
+            Self::GoModule(_) => {
+                let path = package.split('@').next().unwrap_or(package);
+                let mut parts = path.trim_end_matches('/').rsplit('/');
+                let last = parts.next().unwrap_or(path);
+                let major = last.strip_prefix('v').is_some_and(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()));
+                match parts.next() {
+                    Some(parent) if major => parent.to_string(),
+                    _ => last.to_string()
+                }
+            },
+            Self::Flake => {
+                let attribute = package.rsplit('#').next().unwrap_or(package);
+                attribute.rsplit('.').next().unwrap_or(attribute).to_string()
+            }
+        }
+    }
+
+    pub fn install(&self, package: &str) -> String {
+        match self {
+            Self::GoModule(_) if !package.contains('@') => format!("{package}@latest"),
+            Self::Flake if !package.contains('#') => format!("nixpkgs#{package}"),
+            _ => package.to_string()
+        }
+    }
+
+    pub fn uninstall(&self, package: &str) -> String {
+        match self {
+            Self::GoModule(bin) => bin.join(self.installed(package)).display().to_string(),
+            Self::Flake => self.installed(package),
+            Self::Package => package.to_string()
+        }
+    }
+}
+
+fn strings<'a>(args: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    args.into_iter().map(String::from).collect()
+}
+
+fn rpm_versions() -> Query {
+    Query::new(["rpm", "-qa", "--queryformat", "%{NAME} %{VERSION}-%{RELEASE}\n"], Output::FirstColumn)
+}
+
+impl Prog {
+    fn protect(&mut self, base: &[&str]) {
+        self.protected.extend(base.iter().map(|package| package.to_string()));
+    }
+
+    fn maintain(&mut self, upgrade: &[&[&str]], clean: &[&[&str]]) {
+        let commands = |commands: &[&[&str]]| commands.iter().map(|command| command.iter().map(|arg| arg.to_string()).collect()).collect();
+        self.upgrade = commands(upgrade);
+        self.clean = commands(clean);
+    }
+}
+
+impl Version {
+    /// Whether an installed version satisfies a pin, where `*` matches
+    /// anything and the package release after the last `-` may be left out.
+    pub fn matches(pin: &str, installed: &str) -> bool {
+        let pattern = pin.split('*').map(regex::escape).collect::<Vec<String>>().join(".*");
+        let pattern = Regex::new(&format!("^{pattern}$")).unwrap();
+        pattern.is_match(installed) || installed.rsplit_once('-').is_some_and(|(version, _)| pattern.is_match(version))
+    }
+
+    pub fn args(&self, naming: &Naming, package: &Package) -> Vec<String> {
+        match (self, &package.version) {
+            (Self::Suffix(separator), Some(version)) => vec![naming.install(&format!("{}{separator}{version}", package.name))],
+            (Self::Flag(flag), Some(version)) => vec![flag.to_string(), version.to_string(), naming.install(&package.name)],
+            _ => vec![naming.install(&package.name)]
+        }
+    }
 }
 
 pub struct Arch {
@@ -58,54 +307,130 @@ pub struct Fedora {
     pub prog: Prog
 }
 
+pub struct Cargo {
+    pub prog: Prog
+}
+
+pub struct Pipx {
+    pub prog: Prog
+}
+
+pub struct Npm {
+    pub prog: Prog
+}
+
+pub struct Go {
+    pub prog: Prog
+}
+
+pub struct Gem {
+    pub prog: Prog
+}
+
+pub struct Void {
+    pub prog: Prog
+}
+
+pub struct Alpine {
+    pub prog: Prog
+}
+
+pub struct Gentoo {
+    pub prog: Prog
+}
+
+pub struct Nix {
+    pub prog: Prog
+}
+
+pub struct Snap {
+    pub prog: Prog
+}
+
+pub struct Brew {
+    pub prog: Prog
+}
+
 pub trait Builder {
     fn new(prog: &str) -> Self;
 }
 
 impl Builder for Debian {
     fn new(prog: &str) -> Self {
-        Self {
-            prog: Prog::new("sudo",
-                [prog, "-y", "install"],
-                [prog, "-y", "remove"], 
-                [prog, "list", "--installed"]
-            )
-        }
+        let mut prog = Prog::new("sudo", prog,
+            [prog, "-y", "install"],
+            [prog, "-y", "remove"],
+            Query::new(["dpkg-query", "-W", "-f", "${db:Status-Status} ${Package}\n"], Output::Regex(r"^installed (\S+)$".to_string())),
+            Query::new(["apt-mark", "showmanual"], Output::Lines));
+        prog.version = Version::Suffix("=".to_string());
+        prog.versions = Some(Query::new(["dpkg-query", "-W", "-f", "${db:Status-Status} ${Package} ${Version}\n"], Output::Regex(r"^installed (\S+) (\S+)$".to_string())));
+        prog.repin = vec!["--allow-downgrades".to_string()];
+        prog.hold = Hold::Command(strings(["apt-mark", "hold"]), strings(["apt-mark", "unhold"]));
+        prog.protect(&["apt", "dpkg", "base-files", "base-passwd", "libc6", "bash", "coreutils", "init", "systemd", "systemd-sysv", "sudo", "linux-image-amd64", "linux-image-arm64", "grub-pc", "grub-efi-amd64"]);
+        let binary = prog.title.clone();
+        prog.maintain(&[&["sudo", &binary, "update"], &["sudo", &binary, "-y", "upgrade"]], &[&["sudo", &binary, "clean"]]);
+        prog.orphans = Some(Query::new([binary.as_str(), "--simulate", "autoremove"], Output::Regex(r"^Remv (\S+)".to_string())));
+        Self { prog }
     }
 }
 
 impl Builder for OpenSUSE {
     fn new(prog: &str) -> Self {
-        Self {
-            prog: Prog::new("sudo",
+        let mut prog = Prog::new("sudo", prog,
             [prog, "-n", "install"],
             [prog, "-n", "remove"],
-            [prog, "search", "-i"])
-        }
+            Query::new(["rpm", "-qa", "--queryformat", "%{NAME}\n"], Output::Lines),
+            Query::new(["zypper", "--quiet", "packages", "--userinstalled"], Output::Regex(r"^i\+?\s*\|[^|]*\|\s*([^\s|]+)".to_string())));
+        prog.version = Version::Suffix("=".to_string());
+        prog.versions = Some(rpm_versions());
+        prog.repin = vec!["--oldpackage".to_string()];
+        prog.hold = Hold::Command(strings(["zypper", "addlock"]), strings(["zypper", "removelock"]));
+        prog.protect(&["zypper", "rpm", "filesystem", "glibc", "bash", "coreutils", "systemd", "sudo", "kernel-default", "grub2"]);
+        let binary = prog.title.clone();
+        prog.maintain(&[&["sudo", &binary, "-n", "dup"]], &[&["sudo", &binary, "clean", "--all"]]);
+        prog.orphans = Some(Query::new(["zypper", "--quiet", "packages", "--unneeded"], Output::Regex(r"^i\+?\s*\|[^|]*\|\s*([^\s|]+)".to_string())));
+        Self { prog }
     }
 }
 
 impl Builder for Fedora {
     fn new(prog: &str) -> Self {
-        Self {
-            prog: Prog::new("sudo",
+        let mut prog = Prog::new("sudo", prog,
             [prog, "-y", "install"],
-            [prog, "-y", "uninstall"], 
-            [prog, "--installed", "list"])
-        }
+            [prog, "-y", "uninstall"],
+            Query::new(["rpm", "-qa", "--queryformat", "%{NAME}\n"], Output::Lines),
+            Query::new(["dnf", "repoquery", "--userinstalled", "--queryformat", "%{name}\n"], Output::Lines));
+        prog.version = Version::Suffix("-".to_string());
+        prog.versions = Some(rpm_versions());
+        prog.hold = Hold::Command(strings(["dnf", "versionlock", "add"]), strings(["dnf", "versionlock", "delete"]));
+        prog.protect(&["dnf", "dnf5", "rpm", "filesystem", "glibc", "bash", "coreutils", "systemd", "sudo", "kernel", "kernel-core", "grub2-common"]);
+        let binary = prog.title.clone();
+        prog.maintain(&[&["sudo", &binary, "-y", "upgrade"]], &[&["sudo", &binary, "clean", "all"]]);
+        prog.orphans = Some(Query::new(["dnf", "repoquery", "--unneeded", "--queryformat", "%{name}\n"], Output::Lines));
+        Self { prog }
     }
 }
 
 impl Builder for Arch {
     fn new(prog: &str) -> Self {
-        let (mut app,mut install, mut uninstall, mut checker) = (prog, vec!["-S", "--noconfirm"], vec!["-Rns", "--noconfirm"], vec!["-Q"]); 
+        let (mut app,mut install, mut uninstall) = (prog, vec!["-S", "--noconfirm"], vec!["-Rns", "--noconfirm"]); 
         match prog {
             "pacman" => { 
-                app = "sudo"; install.insert(0, prog); uninstall.insert(0, prog); checker.insert(0, prog) 
+                app = "sudo"; install.insert(0, prog); uninstall.insert(0, prog)
             },
             &_ => {}
         }
-        Self { prog: Prog::new(app, install, uninstall, checker)  }
+        let (checker, explicit) = (Query::new([prog, "-Qq"], Output::Lines), Query::new([prog, "-Qqe"], Output::Lines));
+        let orphans = Query::new([prog, "-Qdtq"], Output::Lines);
+        let mut prog = Prog::new(app, prog, install, uninstall, checker, explicit);
+        prog.hold = Hold::IgnorePkg;
+        match app {
+            "sudo" => prog.maintain(&[&["sudo", "pacman", "-Syu", "--noconfirm"]], &[&["sudo", "pacman", "-Sc", "--noconfirm"]]),
+            helper => prog.maintain(&[&["sudo", "pacman", "-Syu", "--noconfirm"], &[helper, "-Sua", "--noconfirm"]], &[&[helper, "-Sc", "--noconfirm"]])
+        }
+        prog.orphans = Some(orphans);
+        prog.protect(&["base", "base-devel", "filesystem", "glibc", "bash", "coreutils", "systemd", "pacman", "sudo", "linux", "linux-lts", "linux-zen", "linux-hardened", "linux-firmware", "mkinitcpio", "grub", "efibootmgr"]);
+        Self { prog }
     }
 }
 
@@ -116,31 +441,201 @@ impl Builder for Vsc {
             "vscodium" => { _app = prog }
             &_ => { _app = "code" }
         }
-        Self {
-            prog: Prog::new(
-                _app, 
-                ["--install-extension"],
-                ["--uninstall-extension"],
-                ["--list-extensions"])
-        }
+        let mut prog = Prog::new(
+            _app, _app,
+            ["--install-extension"],
+            ["--uninstall-extension"],
+            Query::new([_app, "--list-extensions"], Output::Lines),
+            Query::new([_app, "--list-extensions"], Output::Lines));
+        prog.ignore_case = true;
+        prog.repeat = true;
+        prog.version = Version::Suffix("@".to_string());
+        prog.maintain(&[&[_app, "--update-extensions"]], &[]);
+        Self { prog }
     }
 }
 
 impl Builder for Flatpak {
     fn new(prog: &str) -> Self {
-        Self {
-            prog: Prog::new(
-                prog,
-                ["install", "-y"],
-                ["uninstall", "-y"],
-            ["list"])
-        }
+        let mut prog = Prog::new(
+            prog, prog,
+            ["install", "-y"],
+            ["uninstall", "-y"],
+            Query::new([prog, "list", "--columns=application"], Output::Lines),
+            Query::new([prog, "list", "--app", "--columns=application"], Output::Lines));
+        prog.maintain(&[&["flatpak", "update", "-y"]], &[&["flatpak", "uninstall", "--unused", "-y"]]);
+        Self { prog }
     }
 }
 
+impl Builder for Cargo {
+    fn new(prog: &str) -> Self {
+        let list = || Query::new([prog, "install", "--list"], Output::Regex(r"^(\S+) v\S+:$".to_string()));
+        let mut prog = Prog::new(
+            prog, prog,
+            ["install"],
+            ["uninstall"],
+            list(),
+            list());
+        prog.version = Version::Flag("--version".to_string());
+        prog.versions = Some(Query::new([prog.prog.as_str(), "install", "--list"], Output::Regex(r"^(\S+) v(\S+):$".to_string())));
+        prog.reinstall = true;
+        Self { prog }
+    }
+}
 
+impl Builder for Pipx {
+    fn new(prog: &str) -> Self {
+        let list = || Query::new([prog, "list", "--short"], Output::FirstColumn);
+        let mut prog = Prog::new(
+            prog, prog,
+            ["install"],
+            ["uninstall"],
+            list(),
+            list());
+        prog.version = Version::Suffix("==".to_string());
+        prog.versions = Some(list());
+        prog.repin = vec!["--force".to_string()];
+        prog.maintain(&[&["pipx", "upgrade-all"]], &[]);
+        Self { prog }
+    }
+}
 
+impl Builder for Npm {
+    fn new(prog: &str) -> Self {
+        let list = || Query::new([prog, "ls", "--global", "--depth=0", "--parseable"], Output::Regex(r"node_modules/((?:@[^/]+/)?[^/]+)$".to_string()));
+        let mut prog = Prog::new(
+            prog, prog,
+            ["install", "--global"],
+            ["uninstall", "--global"],
+            list(),
+            list());
+        prog.version = Version::Suffix("@".to_string());
+        prog.versions = Some(Query::new([prog.prog.as_str(), "ls", "--global", "--depth=0"], Output::Regex(r"── ((?:@[^/@\s]+/)?[^@\s]+)@(\S+)$".to_string())));
+        prog.maintain(&[&["npm", "update", "--global"]], &[&["npm", "cache", "clean", "--force"]]);
+        Self { prog }
+    }
+}
 
+impl Builder for Go {
+    fn new(prog: &str) -> Self {
+        let bin = env::var("GOBIN").map(PathBuf::from)
+            .or_else(|_| env::var("GOPATH").map(|path| PathBuf::from(path).join("bin")))
+            .unwrap_or_else(|_| home_dir().unwrap().join("go/bin"));
+        let dir = bin.display().to_string();
+        let list = || Query::new(["ls", "-1", &dir], Output::Lines);
+        let mut prog = Prog::new(
+            prog, prog,
+            ["install"],
+            ["-f"],
+            list(),
+            list());
+        prog.batch = false;
+        prog.naming = Naming::GoModule(bin);
+        prog.version = Version::Suffix("@".to_string());
+        prog.reinstall = true;
+        prog.maintain(&[], &[&["go", "clean", "-cache"]]);
+        Self { prog }
+    }
+}
 
+impl Builder for Gem {
+    fn new(prog: &str) -> Self {
+        let list = || Query::new([prog, "list", "--no-versions"], Output::Regex(r"^([^\s*]+)$".to_string()));
+        let mut prog = Prog::new(
+            prog, prog,
+            ["install"],
+            ["uninstall", "--all", "--executables"],
+            list(),
+            list());
+        prog.version = Version::Flag("--version".to_string());
+        prog.maintain(&[&["gem", "update"]], &[&["gem", "cleanup"]]);
+        Self { prog }
+    }
+}
 
+impl Builder for Void {
+    fn new(prog: &str) -> Self {
+        let mut prog = Prog::new("sudo", prog,
+            ["xbps-install", "--sync", "--yes"],
+            ["xbps-remove", "--recursive", "--yes"],
+            Query::new(["xbps-query", "--list-pkgs"], Output::Regex(r"^ii\s+(\S+)-[^-\s]+\s".to_string())),
+            Query::new(["xbps-query", "--list-manual-pkgs"], Output::Regex(r"^(\S+)-[^-\s]+$".to_string())));
+        prog.hold = Hold::Command(strings(["xbps-pkgdb", "-m", "hold"]), strings(["xbps-pkgdb", "-m", "unhold"]));
+        prog.protect(&["base-system", "xbps", "glibc", "musl", "bash", "coreutils", "runit-void", "sudo", "linux", "grub"]);
+        prog.maintain(&[&["sudo", "xbps-install", "--sync", "--update", "--yes"]], &[&["sudo", "xbps-remove", "--clean-cache", "--yes"]]);
+        prog.orphans = Some(Query::new(["xbps-query", "--list-orphans"], Output::Regex(r"^(\S+)-[^-\s]+$".to_string())));
+        Self { prog }
+    }
+}
 
+impl Builder for Alpine {
+    fn new(prog: &str) -> Self {
+        let mut prog = Prog::new("sudo", prog,
+            ["apk", "add", "--no-interactive"],
+            ["apk", "del", "--no-interactive"],
+            Query::new(["apk", "info"], Output::Lines),
+            Query::new(["cat", "/etc/apk/world"], Output::Regex(r"^([^\s<>=~]+)".to_string())));
+        prog.version = Version::Suffix("=".to_string());
+        prog.protect(&["alpine-base", "apk-tools", "busybox", "musl", "openrc", "linux-lts"]);
+        prog.maintain(&[&["sudo", "apk", "upgrade", "--update-cache"]], &[&["sudo", "apk", "cache", "clean"]]);
+        Self { prog }
+    }
+}
+
+impl Builder for Gentoo {
+    fn new(prog: &str) -> Self {
+        let mut prog = Prog::new("sudo", prog,
+            ["emerge", "--noreplace", "--quiet-build"],
+            ["emerge", "--unmerge"],
+            Query::new(["find", "/var/db/pkg", "-mindepth", "2", "-maxdepth", "2", "-type", "d"], Output::Regex(r"^/var/db/pkg/([^/]+/.+)-\d[^-/]*(?:-r\d+)?$".to_string())),
+            Query::new(["cat", "/var/lib/portage/world"], Output::Lines));
+        prog.protect(&["sys-apps/portage", "sys-apps/baselayout", "sys-libs/glibc", "app-shells/bash", "sys-apps/coreutils", "sys-kernel/gentoo-kernel-bin"]);
+        prog.maintain(&[&["sudo", "emerge", "--sync"], &["sudo", "emerge", "--update", "--deep", "--newuse", "@world"]], &[]);
+        Self { prog }
+    }
+}
+
+impl Builder for Nix {
+    fn new(prog: &str) -> Self {
+        let list = || Query::new([prog, "profile", "list"], Output::Regex(r"^Name:\s+(\S+)$".to_string()));
+        let mut prog = Prog::new(
+            prog, prog,
+            ["profile", "install"],
+            ["profile", "remove"],
+            list(),
+            list());
+        prog.naming = Naming::Flake;
+        prog.maintain(&[&["nix", "profile", "upgrade", "--all"]], &[&["nix", "store", "gc"]]);
+        Self { prog }
+    }
+}
+
+impl Builder for Snap {
+    fn new(prog: &str) -> Self {
+        let list = || Query::new([prog, "list"], Output::Regex(r"^(\S+)\s+\S+\s+\d+\s".to_string()));
+        let mut prog = Prog::new("sudo", prog,
+            [prog, "install"],
+            [prog, "remove"],
+            list(),
+            list());
+        let binary = prog.title.clone();
+        prog.maintain(&[&["sudo", &binary, "refresh"]], &[]);
+        Self { prog }
+    }
+}
+
+impl Builder for Brew {
+    fn new(prog: &str) -> Self {
+        let mut prog = Prog::new(
+            "brew", prog,
+            ["install", "--formula"],
+            ["uninstall", "--formula"],
+            Query::new(["brew", "list", "--formula", "-1"], Output::Lines),
+            Query::new(["brew", "leaves", "--installed-on-request"], Output::Lines));
+        prog.version = Version::Suffix("@".to_string());
+        prog.maintain(&[&["brew", "update"], &["brew", "upgrade"]], &[&["brew", "cleanup"]]);
+        prog.orphans = Some(Query::new(["brew", "autoremove", "--dry-run"], Output::Regex(r"^([^=\s]\S*)$".to_string())));
+        Self { prog }
+    }
+}
@@ -0,0 +1,66 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use crate::{connect::Connect, manage_data::tools::get_table};
+
+use super::init::{report, Err, Install};
+
+enum Maintenance {
+    Upgrade,
+    Clean,
+}
+
+impl Connect {
+    pub fn upgrade(&mut self, args: &[String]) -> Result<(), Err> {
+        self.maintain(args, Maintenance::Upgrade)
+    }
+
+    pub fn clean(&mut self, args: &[String]) -> Result<(), Err> {
+        self.maintain(args, Maintenance::Clean)
+    }
+
+    /// Runs the upgrade or cleanup commands of every manager in `[install]`,
+    /// or only of the managers and groups given as arguments.
+    fn maintain(&mut self, args: &[String], maintenance: Maintenance) -> Result<(), Err> {
+        let args = self.sub_args(args, &["-p", "--profile"], &[]);
+        let conf = self.read_config();
+        let mut installer = Install::new();
+        installer.managers(conf.get("managers"));
+        installer.only = args.positional.iter().map(|title| title.to_lowercase()).collect();
+        let profiles: Vec<String> = args.options(&["-p", "--profile"]).iter().map(|profile| profile.to_lowercase()).collect();
+        if profiles.is_empty() {
+            installer.profiles(None);
+        } else {
+            installer.profiles = profiles;
+        }
+        if let Some(install) = conf.get("install") {
+            for (title, inst) in get_table("install", install) {
+                if installer.selected(&title) {
+                    installer.matches(&title, &inst)?
+                }
+            }
+        }
+        let mut failures = Vec::new();
+        for (prog, _) in installer.progs() {
+            failures.extend(match maintenance {
+                Maintenance::Upgrade => prog.upgrade_all(),
+                Maintenance::Clean => prog.clean_all()
+            });
+        }
+        report(&failures);
+        Ok(())
+    }
+}
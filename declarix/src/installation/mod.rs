@@ -15,5 +15,12 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
 pub mod init;
+pub mod import;
+pub mod maintenance;
 mod installers;
-mod database;
\ No newline at end of file
+mod database;
+mod distro;
+mod custom;
+pub mod package;
+mod repositories;
+mod groups;
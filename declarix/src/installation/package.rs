@@ -0,0 +1,112 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::process::exit;
+use colored::Colorize;
+use toml::Value;
+
+enum PackageError {
+    MissingName(String),
+    InvalidField(String, &'static str),
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::MissingName(package) => {
+                writeln!(f, "\tPackage entry without a name: {}", package.red())?;
+                writeln!(f, "\tExpected: {}", "{ name = \"nodejs\", version = \"20.*\" }".yellow())?
+            },
+            Self::InvalidField(package, field) => {
+                writeln!(f, "\tInvalid {} for package {}", field.red(), package.red())?
+            }
+        }
+        exit(1);
+    }
+}
+
+/// A single entry of an `[install]` array, either `"name"` or
+/// `{ name, version, options, hold }`.
+pub struct Package {
+    pub name: String,
+    pub version: Option<String>,
+    pub options: Vec<String>,
+    pub hold: bool,
+}
+
+impl Package {
+    pub fn new(value: &Value) -> Self {
+        let Value::Table(table) = value else {
+            return Self {
+                name: Self::name(value),
+                version: None,
+                options: Vec::new(),
+                hold: false
+            }
+        };
+        let name = Self::name(value);
+        let invalid = |field: &'static str| -> ! {
+            println!("{}",PackageError::InvalidField(name.to_string(), field));
+            exit(1)
+        };
+        let version = match table.get("version") {
+            None => None,
+            Some(Value::String(version)) => Some(version.to_string()),
+            Some(_) => invalid("version")
+        };
+        let options = match table.get("options") {
+            None => Vec::new(),
+            Some(Value::Array(options)) => options.iter()
+                .map(|option| option.as_str().map(String::from).unwrap_or_else(|| invalid("options")))
+                .collect(),
+            Some(_) => invalid("options")
+        };
+        let hold = match table.get("hold") {
+            None => false,
+            Some(Value::Boolean(hold)) => *hold,
+            Some(_) => invalid("hold")
+        };
+        Self { name, version, options, hold }
+    }
+
+    pub fn name(value: &Value) -> String {
+        match value {
+            Value::String(name) => name.to_string(),
+            Value::Table(table) => match table.get("name") {
+                Some(Value::String(name)) => name.to_string(),
+                _ => {
+                    println!("{}",PackageError::MissingName(value.to_string()));
+                    exit(1)
+                }
+            },
+            _ => {
+                println!("{}",PackageError::MissingName(value.to_string()));
+                exit(1)
+            }
+        }
+    }
+
+    pub fn names(values: &[Value]) -> Vec<String> {
+        values.iter().map(Self::name).collect()
+    }
+
+    /// Packages that can share a single install command with others.
+    pub fn plain(&self) -> bool {
+        self.version.is_none() && self.options.is_empty()
+    }
+}
@@ -0,0 +1,341 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::{env, fs, path::{Path, PathBuf}, process::exit};
+use colored::Colorize;
+use toml::Value;
+
+use crate::manage_data::tools::{copy_root, find_program, get_buffer, get_prefixed_buffer, get_table, missing_program, remove_root, write_root};
+
+use super::database::database::PackStatements;
+
+pub const MANAGERS: [&str; 5] = ["apt", "pacman", "dnf", "zypper", "flatpak"];
+const PACMAN_INCLUDE: &str = "Include = /etc/pacman.d/declarix/*.conf";
+
+enum RepoError {
+    InvalidManager(String),
+    MissingField(String, &'static str),
+    InvalidField(String, &'static str),
+}
+
+impl fmt::Display for RepoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::InvalidManager(manager) => {
+                writeln!(f, "\tRepositories are not supported for: {}", manager.red())?;
+                writeln!(f, "\tExpected one of: {}", MANAGERS.join(", ").yellow())?
+            },
+            Self::MissingField(repository, field) => {
+                writeln!(f, "\t[repositories.{}] is missing {}", repository.red(), field.red())?
+            },
+            Self::InvalidField(repository, field) => {
+                writeln!(f, "\tInvalid {} in [repositories.{}]", field.red(), repository)?
+            }
+        }
+        exit(1);
+    }
+}
+
+/// A third-party source declared under `[repositories.<manager>.<name>]`.
+pub struct Repository {
+    manager: String,
+    name: String,
+    url: String,
+    key: Option<String>,
+    suites: Vec<String>,
+    components: Vec<String>,
+    siglevel: Option<String>,
+}
+
+impl Repository {
+    fn new(manager: &str, name: &str, value: &Value) -> Self {
+        let title = format!("{manager}.{name}");
+        let table = match value {
+            Value::String(url) => {
+                let mut table = toml::map::Map::new();
+                table.insert("url".to_string(), Value::String(url.to_string()));
+                table
+            },
+            _ => get_table(&format!("repositories.{title}"), value)
+        };
+        let string = |field: &'static str| match table.get(field) {
+            None => None,
+            Some(Value::String(value)) => Some(value.to_string()),
+            Some(_) => {
+                println!("{}",RepoError::InvalidField(title.to_string(), field));
+                exit(1)
+            }
+        };
+        let list = |field: &'static str| match table.get(field) {
+            None => Vec::new(),
+            Some(Value::String(value)) => value.split_whitespace().map(String::from).collect(),
+            Some(Value::Array(values)) => values.iter().map(|value| value.as_str().map(String::from).unwrap_or_else(|| {
+                println!("{}",RepoError::InvalidField(title.to_string(), field));
+                exit(1)
+            })).collect(),
+            Some(_) => {
+                println!("{}",RepoError::InvalidField(title.to_string(), field));
+                exit(1)
+            }
+        };
+        let Some(url) = string("url") else {
+            println!("{}",RepoError::MissingField(title, "url"));
+            exit(1)
+        };
+        let repository = Self {
+            manager: manager.to_string(),
+            name: name.to_string(),
+            url,
+            key: string("key"),
+            suites: list("suites"),
+            components: list("components"),
+            siglevel: string("siglevel"),
+        };
+        if manager == "apt" && repository.suites.is_empty() {
+            println!("{}",RepoError::MissingField(title, "suites"));
+            exit(1)
+        }
+        repository
+    }
+
+    fn path(manager: &str, name: &str) -> Option<PathBuf> {
+        match manager {
+            "apt" => Some(PathBuf::from(format!("/etc/apt/sources.list.d/declarix-{name}.sources"))),
+            "pacman" => Some(PathBuf::from(format!("/etc/pacman.d/declarix/{name}.conf"))),
+            "dnf" => Some(PathBuf::from(format!("/etc/yum.repos.d/declarix-{name}.repo"))),
+            "zypper" => Some(PathBuf::from(format!("/etc/zypp/repos.d/declarix-{name}.repo"))),
+            _ => None
+        }
+    }
+
+    fn keyring(name: &str, key: &str) -> PathBuf {
+        let extension = if key.ends_with(".gpg") { "gpg" } else { "asc" };
+        PathBuf::from(format!("/etc/apt/keyrings/declarix-{name}.{extension}"))
+    }
+
+    /// The source file. An apt source only points at its keyring when `signed`,
+    /// and notes the key URL so that a changed key is fetched again.
+    fn contents(&self, signed: bool) -> String {
+        match self.manager.as_str() {
+            "apt" => {
+                let mut contents = String::new();
+                if let Some(key) = &self.key {
+                    contents.push_str(&format!("# Key: {key}\n"));
+                }
+                contents.push_str(&format!("Types: deb\nURIs: {}\nSuites: {}\n", self.url, self.suites.join(" ")));
+                if !self.components.is_empty() {
+                    contents.push_str(&format!("Components: {}\n", self.components.join(" ")));
+                }
+                if let Some(key) = self.key.as_ref().filter(|_| signed) {
+                    contents.push_str(&format!("Signed-By: {}\n", Self::keyring(&self.name, key).display()));
+                }
+                contents
+            },
+            "pacman" => {
+                let mut contents = format!("[{}]\n", self.name);
+                if let Some(siglevel) = &self.siglevel {
+                    contents.push_str(&format!("SigLevel = {siglevel}\n"));
+                }
+                contents.push_str(&format!("Server = {}\n", self.url));
+                contents
+            },
+            _ => {
+                let mut contents = format!("[declarix-{0}]\nname={0}\nbaseurl={1}\nenabled=1\n", self.name, self.url);
+                if self.manager == "zypper" {
+                    contents.push_str("autorefresh=1\ntype=rpm-md\n");
+                }
+                match &self.key {
+                    Some(key) => contents.push_str(&format!("gpgcheck=1\ngpgkey={key}\n")),
+                    None => contents.push_str("gpgcheck=0\n")
+                }
+                contents
+            }
+        }
+    }
+
+    /// Returns whether anything on the system changed. `program` is where the
+    /// manager is found.
+    fn apply(&self, program: &str) -> bool {
+        if let Some(reason) = missing_program(program) {
+            println!("{}",format!("Skipping repository {}: {reason}",self.name).yellow());
+            return false
+        }
+        let Some(path) = Self::path(&self.manager, &self.name) else {
+            get_buffer(program, &vec!["remote-add".to_string(), "--if-not-exists".to_string()], &vec![self.name.to_string(), self.url.to_string()]);
+            return false
+        };
+        let mut changed = false;
+        let current = fs::read_to_string(&path).ok();
+        let mut signed = false;
+        if let Some(key) = self.key.as_ref().filter(|_| self.manager == "apt") {
+            let keyring = Self::keyring(&self.name, key);
+            if !keyring.exists() || current.as_deref() != Some(self.contents(true).as_str()) {
+                changed = self.fetch_key(key, &keyring);
+            }
+            signed = keyring.exists();
+        }
+        let contents = self.contents(signed);
+        if current.as_deref() != Some(contents.as_str()) {
+            changed |= write_root(&path, contents);
+        }
+        changed
+    }
+
+    /// Downloads an apt signing key into its keyring, returning whether the
+    /// keyring changed.
+    fn fetch_key(&self, key: &str, keyring: &Path) -> bool {
+        let staged = env::temp_dir().join(keyring.file_name().unwrap());
+        let fetched = get_prefixed_buffer(&format!("[{}]",self.name).cyan(), "curl", &["-fsSL".to_string(), "-o".to_string(), staged.display().to_string()], &[key.to_string()]);
+        let changed = match fetched {
+            Ok(()) => fs::read(&staged).ok() != fs::read(keyring).ok() && copy_root(&staged, keyring),
+            Err(err) => {
+                println!("{}",format!("Could not fetch the key of repository {}, leaving it unsigned: {err}",self.name).red());
+                false
+            }
+        };
+        if staged.exists() {
+            fs::remove_file(&staged).unwrap();
+        }
+        changed
+    }
+
+    fn remove(manager: &str, name: &str, program: &str) {
+        match Self::path(manager, name) {
+            Some(path) => {
+                remove_root(&path);
+                if manager == "apt" {
+                    for extension in ["asc", "gpg"] {
+                        remove_root(&Self::keyring(name, &format!("key.{extension}")));
+                    }
+                }
+            },
+            None if find_program(program).is_some() => get_buffer(program, &vec!["remote-delete".to_string(), "--force".to_string()], &vec![name.to_string()]),
+            None => {}
+        }
+    }
+}
+
+pub struct Repositories {
+    pub repositories: Vec<Repository>,
+    /// Managers whose `path` is overridden under `[managers]`.
+    pub programs: Vec<(String, String)>,
+}
+
+impl Repositories {
+    pub fn new(repositories: Option<&Value>) -> Self {
+        let mut list = Vec::new();
+        if let Some(repositories) = repositories {
+            for (manager, entries) in get_table("repositories", repositories) {
+                let manager = manager.to_lowercase();
+                if !MANAGERS.contains(&manager.as_str()) {
+                    println!("{}",RepoError::InvalidManager(manager));
+                    exit(1)
+                }
+                for (name, value) in get_table(&format!("repositories.{manager}"), &entries) {
+                    list.push(Repository::new(&manager, &name, &value))
+                }
+            }
+        }
+        Self { repositories: list, programs: Vec::new() }
+    }
+
+    pub fn program(&self, manager: &str) -> String {
+        self.programs.iter()
+            .find(|(name, _)| name == manager)
+            .map_or(manager.to_string(), |(_, program)| program.to_string())
+    }
+
+    /// Adds declared repositories, removes ones dropped from the config and
+    /// refreshes the metadata of every manager that changed.
+    pub fn structure(&self, statements: &mut PackStatements) {
+        let mut changed = Vec::new();
+        for repository in &self.repositories {
+            if repository.apply(&self.program(&repository.manager)) && !changed.contains(&repository.manager.as_str()) {
+                changed.push(repository.manager.as_str());
+            }
+            if statements.update.execute((&repository.name, &repository.manager)).unwrap() == 0 {
+                statements.insert.execute((&repository.name, &repository.manager)).unwrap();
+            }
+        }
+        for manager in MANAGERS {
+            let removed: Vec<String> = statements.select.query_map([manager], |row| row.get(0)).unwrap()
+                .filter_map(Result::ok)
+                .collect();
+            for name in &removed {
+                Repository::remove(manager, name, &self.program(manager));
+            }
+            if !removed.is_empty() && !changed.contains(&manager) {
+                changed.push(manager);
+            }
+            statements.remove.execute([manager]).unwrap();
+            statements.zero.execute([manager]).unwrap();
+        }
+        if self.repositories.iter().any(|repository| repository.manager == "pacman") {
+            let conf = fs::read_to_string("/etc/pacman.conf").unwrap_or_default();
+            if !conf.lines().any(|line| line.trim() == PACMAN_INCLUDE) {
+                get_buffer("sudo", &vec!["sed".to_string(), "-i".to_string(), format!("$a {PACMAN_INCLUDE}")], &vec!["/etc/pacman.conf".to_string()]);
+            }
+        }
+        for manager in changed {
+            let refresh: &[&str] = match manager {
+                "apt" => &["apt-get", "update"],
+                "pacman" => &["pacman", "-Syu", "--noconfirm"],
+                "dnf" => &["dnf", "makecache"],
+                "zypper" => &["zypper", "--non-interactive", "--gpg-auto-import-keys", "refresh"],
+                _ => continue
+            };
+            let mut refresh: Vec<String> = refresh.iter().map(|arg| arg.to_string()).collect();
+            if self.programs.iter().any(|(name, _)| name == manager) {
+                refresh[0] = self.program(manager);
+            }
+            if find_program(&refresh[0]).is_some() {
+                if let Err(err) = get_prefixed_buffer(&format!("[{manager}]").cyan(), "sudo", &refresh, &[]) {
+                    println!("{}",format!("Could not refresh {manager} after changing its repositories: {err}").red());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Repositories;
+
+    #[test]
+    fn repositories_render_source_files() {
+        let repositories: toml::Value = toml::from_str(r#"
+            [apt.docker]
+            url = "https://download.docker.com/linux/debian"
+            suites = "bookworm"
+            components = ["stable"]
+            key = "https://download.docker.com/linux/debian/gpg"
+
+            [dnf.docker]
+            url = "https://download.docker.com/linux/fedora/$releasever/$basearch/stable"
+
+            [flatpak]
+            flathub = "https://dl.flathub.org/repo/flathub.flatpakrepo"
+        "#).unwrap();
+        let repositories = Repositories::new(Some(&repositories)).repositories;
+        assert_eq!(repositories.len(), 3);
+        assert_eq!(repositories[0].contents(true), "# Key: https://download.docker.com/linux/debian/gpg\nTypes: deb\nURIs: https://download.docker.com/linux/debian\nSuites: bookworm\nComponents: stable\nSigned-By: /etc/apt/keyrings/declarix-docker.asc\n");
+        assert!(!repositories[0].contents(false).contains("Signed-By"));
+        assert_eq!(repositories[1].contents(true), "[declarix-docker]\nname=docker\nbaseurl=https://download.docker.com/linux/fedora/$releasever/$basearch/stable\nenabled=1\ngpgcheck=0\n");
+        assert_eq!(repositories[2].url, "https://dl.flathub.org/repo/flathub.flatpakrepo");
+    }
+}
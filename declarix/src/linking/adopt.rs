@@ -0,0 +1,167 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::{fs, io, path::{self, PathBuf}, process::exit};
+use colored::Colorize;
+use dirs::home_dir;
+use shared::Ops;
+
+use crate::{connect::{Connect, Title}, database::database::{Database, PreparedStatements}, manage_data::{config::{ConfigFile, Entry}, tools::{calculate_hash, fixer}}, structures::structs::{Construct, Link, Set, Setting}};
+
+use super::operations::Operation;
+
+enum AdoptError {
+    NoPath,
+    NotFound(String),
+    AlreadyLink(String),
+    InvalidSet(String),
+    NotInHome(String),
+    SourceExists(String),
+    Move(String, io::Error)
+}
+
+impl fmt::Display for AdoptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::NoPath => {
+                writeln!(f, "\tNo path was provided to adopt.")?
+            },
+            Self::NotFound(path) => {
+                writeln!(f, "\tPath does not exist: {}", path.red())?
+            },
+            Self::AlreadyLink(path) => {
+                writeln!(f, "\tPath is already a link: {}", path.red())?
+            },
+            Self::InvalidSet(set) => {
+                writeln!(f, "\tInvalid set: {}", set.red())?;
+                writeln!(f, "\tA path can be adopted into: {}, {} or {}", "home".yellow(), "root".yellow(), "other".yellow())?
+            },
+            Self::NotInHome(path) => {
+                writeln!(f, "\tPath is not inside your home directory: {}", path.red())?
+            },
+            Self::SourceExists(path) => {
+                writeln!(f, "\tSource already exists in the config repository: {}", path.red())?
+            },
+            Self::Move(path, err) => {
+                writeln!(f, "\tProblem moving: {}", path.red())?;
+                writeln!(f, "\t{}", err)?
+            }
+        }
+        exit(1);
+    }
+}
+
+impl Connect {
+    pub fn adopt(&mut self, args: &[String]) {
+        let args = self.sub_args(args, &["--set", "--setting"], &[]);
+        let Some(path) = args.positional.first() else {
+            println!("{}",AdoptError::NoPath);
+            return
+        };
+        let set = args.option(&["--set"]).unwrap_or("home").to_lowercase();
+        if !matches!(set.as_str(), "home" | "root" | "other") {
+            println!("{}",AdoptError::InvalidSet(set.to_string()))
+        }
+        let setting = args.option(&["--setting"]).unwrap_or("link").to_lowercase();
+        let setting_type = match Setting::new(&setting) {
+            Ok(setting) => setting,
+            Err(err) => {
+                println!("{}",err);
+                exit(1)
+            }
+        };
+
+        let destination = path::absolute(path).unwrap();
+        if fs::symlink_metadata(&destination).is_err() {
+            println!("{}",AdoptError::NotFound(destination.display().to_string()))
+        }
+        if destination.is_symlink() {
+            println!("{}",AdoptError::AlreadyLink(destination.display().to_string()))
+        }
+        let entry = match set.as_str() {
+            "home" => match destination.strip_prefix(home_dir().unwrap()) {
+                Ok(relative) => fixer(&relative.display().to_string()),
+                Err(_) => {
+                    println!("{}",AdoptError::NotInHome(destination.display().to_string()));
+                    return
+                }
+            },
+            &_ => fixer(&destination.display().to_string())
+        };
+
+        let conf = self.read_config();
+        let mut construct = Construct::new();
+        construct.title = Title::System;
+        construct.setting = setting_type;
+        construct.set = Set::new(&set);
+        construct.source_path = construct.get_locations(&conf.get("locations"), &set);
+        construct.source = format!("{}{}", construct.source_path, entry);
+        construct.destination = destination.display().to_string();
+        construct.hash = calculate_hash(&construct.source, &construct.destination);
+        if fs::symlink_metadata(&construct.source).is_ok() {
+            println!("{}",AdoptError::SourceExists(construct.source.to_string()))
+        }
+
+        if let Err(err) = Link::new(&construct, 0).adopt() {
+            println!("{}",AdoptError::Move(construct.destination.to_string(), err))
+        }
+
+        let db = Database::new();
+        let mut statements = PreparedStatements::new(&db.conn);
+        db.conn.execute("BEGIN TRANSACTION", ()).unwrap();
+        construct.setting_match(&mut statements);
+        for link in &mut construct.linker {
+            if let Err(err) = link.linker(&mut statements) {
+                println!("{}: {}",link.source.display(),err)
+            }
+        }
+        db.conn.execute("COMMIT TRANSACTION", ()).unwrap();
+
+        let source = entry.trim_start_matches("/");
+        let destination = match set.as_str() {
+            "home" => source.to_string(),
+            &_ => entry.to_string()
+        };
+        let mut config = ConfigFile::open(self.conf_path());
+        config.add(&Entry::Link(&setting, &set, source, &destination));
+        config.save().unwrap();
+        println!("Adopted {} into {}", construct.destination.green(), construct.source.blue());
+    }
+}
+
+impl Link {
+    fn adopt(&self) -> Result<(), io::Error> {
+        if let Some(parent) = self.source.parent() {
+            if !parent.exists() {
+                let existing = parent.ancestors().find(|p| p.exists()).unwrap();
+                if self.check_perms(existing) {
+                    fs::create_dir_all(parent)?
+                } else {
+                    self.run_command(Ops::Create_Dir_All, vec![parent])
+                }
+            }
+        }
+        let parent = self.source.parent().map(PathBuf::from).unwrap_or_default();
+        if self.check_perms(&self.destination) && self.check_perms(&parent) {
+            self.operations(Ops::Move, vec![&self.destination, &self.source])
+        } else {
+            self.run_command(Ops::Move, vec![&self.destination, &self.source]);
+            Ok(())
+        }
+    }
+}
@@ -15,5 +15,6 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
 pub mod link;
+pub mod adopt;
 pub mod copy;
 pub mod operations;
\ No newline at end of file
@@ -14,10 +14,10 @@ GNU General Public License for more details.
 You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
-use crate::{removal::select::{Key, Path as Poth}, structures::structs::Link};
+use crate::{manage_data::tools::record_change, removal::select::{Key, Path as Poth}, structures::structs::Link};
 use std::{ffi::OsStr, fs::{self, Metadata}, io, os::unix::fs::{symlink, MetadataExt}, path::{Path, PathBuf}, process::{exit, Command}, time::UNIX_EPOCH};
 use users::{get_current_gid, get_current_uid};
-use shared::{copy_file, Ops};
+use shared::{copy_file, move_path, Ops};
 
 
 
@@ -71,6 +71,7 @@ impl Operation for Poth {}
 
 impl Operation for Link {
     fn operations(&self, op: Ops, args: Vec<impl AsRef<Path> + AsRef<OsStr>>) -> Result<(), std::io::Error> {
+        let change = matches!(op, Ops::Symlink | Ops::Copy | Ops::Hardlink);
         if self.check_perms(&args[0]) {
             match op {
                 Ops::Symlink => {
@@ -88,10 +89,16 @@ impl Operation for Link {
                 Ops::Hardlink => {
                     fs::hard_link(&args[0], &args[1])?;
                 },
+                Ops::Move => {
+                    move_path(&args[0], &args[1])?
+                },
                 _ => {}
             }
         } else {
-            self.run_command(op, args)
+            self.run_command(op, args.iter().collect())
+        }
+        if change {
+            record_change(args[1].as_ref());
         }
         Ok(())
     }
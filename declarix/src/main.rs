@@ -24,6 +24,8 @@ pub mod installation;
 mod database;
 mod connect;
 mod services;
+mod accounts;
+mod host;
 pub mod removal;
 use users::{get_current_gid, get_current_uid};
 use colored::Colorize;
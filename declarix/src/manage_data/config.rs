@@ -0,0 +1,195 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, io, path::{Path, PathBuf}};
+use toml_edit::{Array, DocumentMut, Item, Table, Value};
+
+pub struct ConfigFile {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+pub enum Entry<'a> {
+    Package(&'a str, &'a str),
+    Service(&'a str, &'a str, &'a str),
+    Link(&'a str, &'a str, &'a str, &'a str),
+}
+
+impl <'a>Entry<'a> {
+    fn keys(&self) -> Vec<&'a str> {
+        match self {
+            Self::Package(manager, _) => vec!["install", manager],
+            Self::Service(manager, stype, _) => vec!["services", manager, stype],
+            Self::Link(setting, set, _, _) => vec!["system", setting, set],
+        }
+    }
+
+    fn value(&self) -> Value {
+        match self {
+            Self::Package(_, name) | Self::Service(_, _, name) => Value::from(*name),
+            Self::Link(_, _, source, destination) => Value::Array(Array::from_iter([*source, *destination])),
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Self::Package(_, name) | Self::Service(_, _, name) => {
+                value.as_str().or_else(|| value.as_inline_table().and_then(|table| table.get("name")).and_then(|n| n.as_str())) == Some(name)
+            },
+            Self::Link(_, _, source, _) => {
+                value.as_array().and_then(|link| link.get(0)).and_then(|s| s.as_str()) == Some(source)
+            }
+        }
+    }
+}
+
+impl ConfigFile {
+    pub fn open(path: &Path) -> Self {
+        let conf = fs::read_to_string(path).unwrap();
+        Self {
+            path: path.to_path_buf(),
+            doc: conf.parse::<DocumentMut>().unwrap()
+        }
+    }
+
+    pub fn save(&self) -> Result<(), io::Error> {
+        fs::write(&self.path, self.doc.to_string())
+    }
+
+    pub fn contains(&self, entry: &Entry) -> bool {
+        let mut item = self.doc.as_item();
+        for key in entry.keys() {
+            match item.get(key) {
+                Some(next) => item = next,
+                None => return false
+            }
+        }
+        if item.is_table_like() {
+            item = match item.get("packages") {
+                Some(packages) => packages,
+                None => return false
+            }
+        }
+        item.as_array().is_some_and(|array| array.iter().any(|value| entry.matches(value)))
+    }
+
+    pub fn add(&mut self, entry: &Entry) -> bool {
+        if self.contains(entry) {
+            return false
+        }
+        push_formatted(self.array(&entry.keys()), entry.value());
+        true
+    }
+
+    pub fn remove(&mut self, entry: &Entry) -> bool {
+        let keys = entry.keys();
+        let (last, tables) = keys.split_last().unwrap();
+        let mut table = self.doc.as_table_mut();
+        for key in tables {
+            match table.get_mut(key).and_then(|item| item.as_table_mut()) {
+                Some(next) => table = next,
+                None => return false
+            }
+        }
+        let (table, last) = match table.get_mut(last).and_then(|item| item.as_table_mut()) {
+            Some(inner) => (inner, "packages"),
+            None => (table, *last)
+        };
+        let Some(array) = table.get_mut(last).and_then(|item| item.as_array_mut()) else {
+            return false
+        };
+        let Some(index) = array.iter().position(|value| entry.matches(value)) else {
+            return false
+        };
+        remove_formatted(array, index);
+        if array.is_empty() {
+            table.remove(last);
+        }
+        true
+    }
+
+    fn array(&mut self, keys: &[&str]) -> &mut Array {
+        let (last, tables) = keys.split_last().unwrap();
+        let mut table = self.doc.as_table_mut();
+        for (i, key) in tables.iter().enumerate() {
+            table = table.entry(key).or_insert_with(|| {
+                let mut new = Table::new();
+                new.set_implicit(i + 1 < tables.len());
+                Item::Table(new)
+            }).as_table_mut().unwrap();
+        }
+        if table.get(last).is_some_and(Item::is_table) {
+            table = table.get_mut(last).unwrap().as_table_mut().unwrap();
+            return table.entry("packages").or_insert_with(new_array).as_array_mut().unwrap()
+        }
+        table.entry(last).or_insert_with(new_array).as_array_mut().unwrap()
+    }
+}
+
+fn new_array() -> Item {
+    let mut array = Array::new();
+    array.set_trailing("\n");
+    array.set_trailing_comma(true);
+    Item::Value(Value::Array(array))
+}
+
+fn decor_str(raw: Option<&toml_edit::RawString>) -> String {
+    raw.and_then(|raw| raw.as_str()).unwrap_or("").to_string()
+}
+
+fn push_formatted(array: &mut Array, mut value: Value) {
+    let prefix = match array.iter().last() {
+        Some(last) => {
+            let prefix = decor_str(last.decor().prefix());
+            match prefix.rfind("\n") {
+                Some(i) => prefix[i..].to_string(),
+                None => " ".to_string()
+            }
+        },
+        None if array.trailing().as_str() == Some("\n") => "\n    ".to_string(),
+        None => String::new()
+    };
+    if let Some(last) = array.iter_mut().last() {
+        let suffix = decor_str(last.decor().suffix());
+        last.decor_mut().set_suffix("");
+        value.decor_mut().set_suffix(suffix);
+    }
+    value.decor_mut().set_prefix(prefix);
+    array.push_formatted(value);
+}
+
+/// Removes a value along with the comments on its own line. A comment ending
+/// the line before it is kept, as it belongs to the previous value.
+fn remove_formatted(array: &mut Array, index: usize) {
+    let removed = array.remove(index);
+    let removed_prefix = decor_str(removed.decor().prefix());
+    let kept = removed_prefix.find("\n").map(|i| &removed_prefix[..i]);
+    if let Some(next) = array.get_mut(index) {
+        let prefix = decor_str(next.decor().prefix());
+        let prefix = match (prefix.find("\n"), kept) {
+            (Some(i), Some(kept)) => format!("{kept}{}", &prefix[i..]),
+            (Some(i), None) => prefix[i..].to_string(),
+            (None, _) => removed_prefix.to_string()
+        };
+        next.decor_mut().set_prefix(prefix);
+    } else if let Some(last) = array.iter_mut().last() {
+        last.decor_mut().set_suffix(decor_str(removed.decor().suffix()));
+        let trailing = array.trailing().as_str().unwrap_or("").to_string();
+        if let (Some(i), Some(kept)) = (trailing.find("\n"), kept) {
+            array.set_trailing(format!("{kept}{}", &trailing[i..]));
+        }
+    }
+}
@@ -0,0 +1,254 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::process::exit;
+use colored::Colorize;
+use toml::Value;
+
+use crate::{connect::Connect, installation::init::Install, structures::structs::Setting};
+
+use super::config::{ConfigFile, Entry};
+
+enum EditError {
+    InvalidKind(String),
+    MissingArgument(&'static str),
+    InvalidManager(String),
+    InvalidServiceType(String),
+    InvalidSet(String),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::InvalidKind(kind) => {
+                writeln!(f, "\tInvalid entry type: {}", kind.red())?;
+                writeln!(f, "\tExpected one of: {}, {} or {}", "package".yellow(), "service".yellow(), "link".yellow())?
+            },
+            Self::MissingArgument(argument) => {
+                writeln!(f, "\tMissing argument: {}", argument.red())?
+            },
+            Self::InvalidManager(manager) => {
+                writeln!(f, "\tInvalid manager: {}", manager.red())?
+            },
+            Self::InvalidServiceType(stype) => {
+                writeln!(f, "\tInvalid service type: {}", stype.red())?;
+                writeln!(f, "\tExpected one of: {} or {}", "user".yellow(), "root".yellow())?
+            },
+            Self::InvalidSet(set) => {
+                writeln!(f, "\tInvalid set: {}", set.red())?;
+                writeln!(f, "\tExpected one of: {}, {}, {} or {}", "home".yellow(), "root".yellow(), "other".yellow(), "generic".yellow())?
+            }
+        }
+        exit(1);
+    }
+}
+
+impl Connect {
+    pub fn add(&mut self, args: &[String]) {
+        self.edit(args, true)
+    }
+
+    pub fn remove(&mut self, args: &[String]) {
+        self.edit(args, false)
+    }
+
+    fn edit(&mut self, args: &[String], add: bool) {
+        let args = self.sub_args(args, &["--set", "--setting"], &[]);
+        let mut positional = args.positional.iter().map(String::as_str);
+        let kind = positional.next().unwrap_or_else(|| {
+            println!("{}",EditError::MissingArgument("<package|service|link>"));
+            exit(1)
+        });
+        let mut next = |name: &'static str| positional.next().unwrap_or_else(|| {
+            println!("{}",EditError::MissingArgument(name));
+            exit(1)
+        });
+        let mut config = ConfigFile::open(self.conf_path());
+        let mut entries = Vec::new();
+        match kind {
+            "package" => {
+                let manager = next("<manager>").to_lowercase();
+                let mut install = Install::new();
+                install.managers(self.read_config().get("managers"));
+                if install.matches(&manager, &Value::Array(Vec::new())).is_err() {
+                    println!("{}",EditError::InvalidManager(manager.to_string()))
+                }
+                let names: Vec<&str> = positional.collect();
+                if names.is_empty() {
+                    println!("{}",EditError::MissingArgument("<package>"))
+                }
+                for name in names {
+                    entries.push((format!("[install.{manager}]"), name.to_string(), Entry::Package(&manager, name)))
+                }
+                self.apply(&mut config, entries, add);
+            },
+            "service" => {
+                let manager = next("<manager>").to_lowercase();
+                if !matches!(manager.as_str(), "systemd" | "openrc" | "runit" | "s6" | "dinit") {
+                    println!("{}",EditError::InvalidManager(manager.to_string()))
+                }
+                let stype = next("<user|root>").to_lowercase();
+                if stype != "root" && (stype != "user" || manager != "systemd") {
+                    println!("{}",EditError::InvalidServiceType(stype.to_string()))
+                }
+                let names: Vec<&str> = positional.collect();
+                if names.is_empty() {
+                    println!("{}",EditError::MissingArgument("<service>"))
+                }
+                for name in names {
+                    entries.push((format!("[services.{manager}.{stype}]"), name.to_string(), Entry::Service(&manager, &stype, name)))
+                }
+                self.apply(&mut config, entries, add);
+            },
+            "link" => {
+                let set = args.option(&["--set"]).unwrap_or("home").to_lowercase();
+                if !matches!(set.as_str(), "home" | "root" | "other" | "generic") {
+                    println!("{}",EditError::InvalidSet(set.to_string()))
+                }
+                let setting = args.option(&["--setting"]).unwrap_or("link").to_lowercase();
+                if let Err(err) = Setting::new(&setting) {
+                    println!("{}",err);
+                    exit(1)
+                }
+                let source = next("<source>");
+                let destination = if add { next("<destination>") } else { "" };
+                entries.push((format!("[system.{setting}.{set}]"), source.to_string(), Entry::Link(&setting, &set, source, destination)));
+                self.apply(&mut config, entries, add);
+            },
+            &_ => {
+                println!("{}",EditError::InvalidKind(kind.to_string()))
+            }
+        }
+        config.save().unwrap();
+    }
+
+    fn apply(&self, config: &mut ConfigFile, entries: Vec<(String, String, Entry)>, add: bool) {
+        for (table, name, entry) in entries {
+            if add {
+                if config.add(&entry) {
+                    println!("Added {} to {}", name.green(), table)
+                } else {
+                    println!("{} is already in {}", name.yellow(), table)
+                }
+            } else if config.remove(&entry) {
+                println!("Removed {} from {}", name.red(), table)
+            } else {
+                println!("{} was not found in {}", name.yellow(), table)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::manage_data::config::{ConfigFile, Entry};
+
+    fn edited(name: &str, conf: &str, edit: impl FnOnce(&mut ConfigFile)) -> String {
+        let path = env::temp_dir().join(format!("declarix-edit-{name}.toml"));
+        fs::write(&path, conf).unwrap();
+        let mut config = ConfigFile::open(&path);
+        edit(&mut config);
+        config.save().unwrap();
+        let conf = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        conf
+    }
+
+    #[test]
+    fn adding_keeps_comments_and_order() {
+        let conf = r#"# My machine
+[install]
+# Terminal tools
+apt = [
+    "git", # for work
+    "vim",
+]
+cargo = ["ripgrep"]
+
+[services.systemd]
+user = ["mpd"]
+"#;
+        let conf = edited("add", conf, |config| {
+            assert!(config.add(&Entry::Package("apt", "htop")));
+            assert!(!config.add(&Entry::Package("apt", "git")));
+            assert!(config.add(&Entry::Package("cargo", "bat")));
+            assert!(config.add(&Entry::Service("systemd", "root", "sshd")));
+            assert!(config.add(&Entry::Link("link", "home", "nvim", ".config/nvim")));
+        });
+        assert_eq!(conf, r#"# My machine
+[install]
+# Terminal tools
+apt = [
+    "git", # for work
+    "vim",
+    "htop",
+]
+cargo = ["ripgrep", "bat"]
+
+[services.systemd]
+user = ["mpd"]
+root = [
+    "sshd",
+]
+
+[system.link]
+home = [
+    ["nvim", ".config/nvim"],
+]
+"#);
+    }
+
+    #[test]
+    fn removing_keeps_comments_and_drops_empty_arrays() {
+        let conf = r#"[install]
+# Terminal tools
+apt = [
+    "git", # for work
+    "vim",
+    "htop", # monitoring
+    "tmux",
+]
+cargo = ["ripgrep"]
+go = ["gopls", "delve", "staticcheck"]
+
+[install.pipx]
+packages = ["black"]
+"#;
+        let conf = edited("remove", conf, |config| {
+            assert!(config.remove(&Entry::Package("apt", "vim")));
+            assert!(!config.remove(&Entry::Package("apt", "vim")));
+            assert!(config.remove(&Entry::Package("apt", "tmux")));
+            assert!(config.remove(&Entry::Package("cargo", "ripgrep")));
+            assert!(config.remove(&Entry::Package("go", "delve")));
+            assert!(config.remove(&Entry::Package("pipx", "black")));
+            assert!(!config.remove(&Entry::Service("systemd", "user", "mpd")));
+        });
+        assert_eq!(conf, r#"[install]
+# Terminal tools
+apt = [
+    "git", # for work
+    "htop", # monitoring
+]
+go = ["gopls", "staticcheck"]
+
+[install.pipx]
+"#);
+    }
+}
@@ -15,7 +15,8 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
 pub mod process_paths;
-//pub mod config;
+pub mod config;
+pub mod edit;
 pub mod system;
 pub mod alias;
 pub mod tools;
\ No newline at end of file
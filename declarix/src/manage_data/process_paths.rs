@@ -25,7 +25,7 @@ use dirs::config_dir;
 use toml::{Table, Value};
 use walkdir::WalkDir;
 
-enum ConfigError<'a> {
+pub enum ConfigError<'a> {
     WrongSetting(&'a str, String)
 }
 
@@ -42,7 +42,7 @@ impl <'a>Display for ConfigError<'a> {
 }
 
 impl Setting {
-    fn new(setting: &str) -> Result<Self, ConfigError> {
+    pub fn new(setting: &str) -> Result<Self, ConfigError> {
         match setting {
             "link" => Ok(Self::Link),
             "recursive" => Ok(Self::Recursive),
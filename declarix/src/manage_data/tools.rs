@@ -15,9 +15,10 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
 use core::fmt;
-use std::{fs, hash::{DefaultHasher, Hash, Hasher}, io::{BufRead, BufReader}, path::PathBuf, process::{exit, Command, Stdio}};
-use colored::Colorize;
+use std::{env, ffi::OsStr, fs, hash::{DefaultHasher, Hash, Hasher}, io::{self, BufRead, BufReader, IsTerminal, Write}, os::unix::fs::PermissionsExt, path::{Path, PathBuf}, process::{exit, Command, Stdio}, sync::Mutex, thread, time::{SystemTime, UNIX_EPOCH}};
+use colored::{ColoredString, Colorize};
 use dirs::data_dir;
+use shared::Ops;
 use toml::{map::Map, Value};
 
 enum TomlError<'a> {
@@ -96,14 +97,78 @@ pub fn get_buffer(manager: &str, args: &Vec<String>, prog: &Vec<String>) {
     reader.lines().filter_map(|line| line.ok()).for_each(|line| println!("{}",line))
 }
 
-pub fn checker(prog: &str, checker: &Vec<String>) -> String {
-    String::from_utf8(Command::new(prog).args(checker).stdout(Stdio::piped()).output().unwrap().stdout).unwrap()
+static CHANGED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Records a destination that was linked or copied during this run.
+pub fn record_change(path: &Path) {
+    CHANGED.lock().unwrap().push(path.to_path_buf())
+}
+
+/// Whether anything at or below `path` was linked or copied during this run.
+pub fn changed(path: &Path) -> bool {
+    CHANGED.lock().unwrap().iter().any(|changed| changed.starts_with(path))
+}
+
+static LOG: Mutex<Option<(PathBuf, fs::File)>> = Mutex::new(None);
+
+/// Appends a line to this run's log file, created on first use in the data directory.
+pub fn log_line(line: &str) {
+    let mut log = LOG.lock().unwrap();
+    if log.is_none() {
+        let dir = data_dir().unwrap().join("declarix").join("logs");
+        fs::create_dir_all(&dir).unwrap();
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let path = dir.join(format!("{time}.log"));
+        let file = fs::OpenOptions::new().create(true).append(true).open(&path).unwrap();
+        *log = Some((path, file));
+    }
+    if let Some((_, file)) = log.as_mut() {
+        writeln!(file, "{line}").unwrap();
+    }
+}
+
+pub fn log_path() -> Option<PathBuf> {
+    LOG.lock().unwrap().as_ref().map(|(path, _)| path.to_path_buf())
 }
 
-pub fn convert_to_string<'a>(values: &Vec<Value>) -> Vec<String> {
-    values.iter().map(|value|{
-        get_string(value)
-    }).collect()
+/// Like `get_buffer`, but prefixes every line of stdout and stderr so the
+/// output of managers running at the same time can be told apart. Everything
+/// is also written to the log, and a failed command returns the end of its
+/// error output.
+pub fn get_prefixed_buffer(prefix: &ColoredString, manager: &str, args: &[String], prog: &[String]) -> Result<(), String> {
+    let plain: &str = prefix;
+    log_line(&format!("{} $ {} {}", plain, manager, args.iter().chain(prog).cloned().collect::<Vec<String>>().join(" ")));
+    let mut child = match Command::new(manager).args(args).args(prog).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            log_line(&format!("{} Could not run {manager}: {err}", plain));
+            return Err(format!("Could not run {manager}: {err}"))
+        }
+    };
+    let (stdout, stderr) = (child.stdout.take().unwrap(), child.stderr.take().unwrap());
+    let print = |line: &str| {
+        println!("{} {}", prefix, line);
+        log_line(&format!("{} {}", plain, line));
+    };
+    let errors: Vec<String> = thread::scope(|scope| {
+        let errors = scope.spawn(|| BufReader::new(stderr).lines().map_while(Result::ok).inspect(|line| print(line)).collect());
+        BufReader::new(stdout).lines().map_while(Result::ok).for_each(|line| print(&line));
+        errors.join().unwrap()
+    });
+    let status = child.wait().unwrap();
+    if status.success() {
+        return Ok(())
+    }
+    log_line(&format!("{} {manager} exited with {status}", plain));
+    let message: Vec<&str> = errors.iter().map(String::as_str).filter(|line| !line.trim().is_empty()).collect();
+    match message.len() {
+        0 => Err(format!("{manager} exited with {status}")),
+        len => Err(message[len.saturating_sub(3)..].join("\n"))
+    }
+}
+
+pub fn checker(prog: &str, checker: &Vec<String>) -> String {
+    String::from_utf8(Command::new(prog).args(checker).stdout(Stdio::piped()).output().unwrap().stdout).unwrap()
 }
 
 pub fn create_db(file: &str) -> PathBuf {
@@ -116,4 +181,102 @@ pub fn create_db(file: &str) -> PathBuf {
         fs::File::create(&db).unwrap();
     }
     db
-}
\ No newline at end of file
+}
+
+/// Resolves a program through `PATH`, along with the sbin directories that a
+/// user's `PATH` often leaves out. Anything containing a `/` is taken as a path.
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    let executable = |path: &Path| path.metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0);
+    if program.contains('/') {
+        return Some(PathBuf::from(program)).filter(|path| executable(path))
+    }
+    let path = env::var_os("PATH").unwrap_or_default();
+    env::split_paths(&path)
+        .chain(["/usr/local/sbin", "/usr/sbin", "/sbin"].map(PathBuf::from))
+        .map(|dir| dir.join(program))
+        .find(|path| executable(path))
+}
+
+/// Why a program cannot be run, if it cannot.
+pub fn missing_program(program: &str) -> Option<String> {
+    match find_program(program) {
+        Some(_) => None,
+        None if program.contains('/') => Some(format!("{program} does not exist or is not executable.")),
+        None => Some(format!("{program} was not found in PATH."))
+    }
+}
+
+/// Runs an operation through declarixRoot, printing its stderr when it fails.
+/// Returns whether it succeeded.
+pub fn root_command(op: Ops, args: &[impl AsRef<OsStr>]) -> bool {
+    let output = Command::new("sudo").arg("declarixRoot").arg(op.to_string()).args(args).output().unwrap();
+    if !output.status.success() {
+        let args: Vec<String> = args.iter().map(|arg| arg.as_ref().to_string_lossy().to_string()).collect();
+        println!("{}: declarixRoot {} {} exited with {}","Error".red(),op,args.join(" "),output.status);
+        for line in String::from_utf8_lossy(&output.stderr).lines().filter(|line| !line.trim().is_empty()) {
+            println!("\t{}",line.red());
+        }
+    }
+    output.status.success()
+}
+
+/// Writes a file owned by root by staging it in the data directory and
+/// copying it into place through declarixRoot.
+pub fn write_root(path: &Path, contents: impl AsRef<[u8]>) -> bool {
+    let staging = data_dir().unwrap().join("declarix").join("staging");
+    fs::create_dir_all(&staging).unwrap();
+    let staged = staging.join(path.file_name().unwrap());
+    fs::write(&staged, contents).unwrap();
+    let written = copy_root(&staged, path);
+    fs::remove_file(&staged).unwrap();
+    written
+}
+
+pub fn copy_root(source: &Path, destination: &Path) -> bool {
+    if let Some(parent) = destination.parent().filter(|parent| !parent.exists()) {
+        if !root_command(Ops::Create_Dir_All, &[parent]) {
+            return false
+        }
+    }
+    root_command(Ops::Copy, &[source, destination])
+}
+
+pub fn remove_root(path: &Path) -> bool {
+    !path.exists() || root_command(Ops::Rm_File, &[path])
+}
+
+/// How `confirm` answers: `Force` (-f) asks when it can and agrees otherwise,
+/// `Yes` (-fc, --yes) agrees without asking.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Answer {
+    Ask,
+    Force,
+    Yes,
+}
+
+static ANSWER: Mutex<Answer> = Mutex::new(Answer::Ask);
+
+pub fn answer(answer: Answer) {
+    *ANSWER.lock().unwrap() = answer
+}
+
+pub fn confirm(question: &str) -> bool {
+    let answer = *ANSWER.lock().unwrap();
+    if answer == Answer::Yes {
+        println!("{} {}", question, "[y/N] y (--yes)".yellow());
+        return true
+    }
+    if !io::stdin().is_terminal() {
+        if answer == Answer::Force {
+            println!("{} {}", question, "[y/N] y (--force)".yellow());
+            return true
+        }
+        println!("{} {}", question, "[y/N] n (not a terminal, pass --yes to agree)".yellow());
+        return false
+    }
+    print!("{} [y/N] ", question);
+    io::stdout().flush().unwrap();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer).unwrap();
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
@@ -0,0 +1,270 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use std::{fs, path::{Path, PathBuf}, process::{Command, Stdio}};
+
+use colored::Colorize;
+use toml::Value;
+
+use crate::manage_data::tools::{changed, checker, get_array, get_buffer, missing_program};
+
+use super::{database::ServiceStatements, services::ServicesT, unit::{State, Unit}};
+
+/// Where a backend finds the services enabled at boot.
+enum Listing {
+    Command(Vec<String>),
+    Directory(PathBuf),
+}
+
+/// A service manager other than systemd. Every command is run through sudo
+/// once per service, with `{}` replaced by the service name.
+pub struct Backend {
+    pub title: String,
+    pub binary: String,
+    enable: Vec<String>,
+    disable: Vec<String>,
+    start: Vec<String>,
+    stop: Vec<String>,
+    restart: Vec<String>,
+    status: (Vec<String>, String),
+    list: Listing,
+    commit: Vec<String>,
+}
+
+fn strings<'a>(args: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    args.into_iter().map(String::from).collect()
+}
+
+impl Backend {
+    pub fn openrc() -> Self {
+        Self {
+            title: ServicesT::OpenRC.to_string(),
+            binary: "rc-update".to_string(),
+            enable: strings(["rc-update", "add", "{}", "default"]),
+            disable: strings(["rc-update", "del", "{}", "default"]),
+            start: strings(["rc-service", "{}", "start"]),
+            stop: strings(["rc-service", "{}", "stop"]),
+            restart: strings(["rc-service", "{}", "restart"]),
+            status: (strings(["rc-service", "{}", "status"]), "started".to_string()),
+            list: Listing::Command(strings(["rc-update", "show", "default"])),
+            commit: Vec::new(),
+        }
+    }
+
+    /// Void keeps its services in `/etc/sv` and `/var/service`, Artix in
+    /// `/etc/runit/sv` and `/run/runit/service`.
+    pub fn runit() -> Self {
+        if Path::new("/etc/runit/sv").exists() {
+            Self::runit_in("/etc/runit/sv", "/run/runit/service")
+        } else {
+            Self::runit_in("/etc/sv", "/var/service")
+        }
+    }
+
+    /// `sv` is given full service paths, as its default `SVDIR` only fits Void.
+    fn runit_in(available: &str, enabled: &str) -> Self {
+        let service = format!("{enabled}/{{}}");
+        Self {
+            title: ServicesT::Runit.to_string(),
+            binary: "sv".to_string(),
+            enable: vec!["ln".to_string(), "-s".to_string(), format!("{available}/{{}}"), format!("{enabled}/")],
+            disable: strings(["rm", &service]),
+            start: strings(["sv", "up", &service]),
+            stop: strings(["sv", "down", &service]),
+            restart: strings(["sv", "restart", &service]),
+            status: (strings(["sv", "status", &service]), "run:".to_string()),
+            list: Listing::Directory(PathBuf::from(enabled)),
+            commit: Vec::new(),
+        }
+    }
+
+    pub fn s6() -> Self {
+        Self {
+            title: ServicesT::S6.to_string(),
+            binary: "s6-rc".to_string(),
+            enable: strings(["s6-service", "add", "default", "{}"]),
+            disable: strings(["s6-service", "delete", "default", "{}"]),
+            start: strings(["s6-rc", "-u", "change", "{}"]),
+            stop: strings(["s6-rc", "-d", "change", "{}"]),
+            restart: strings(["s6-svc", "-r", "/run/service/{}"]),
+            status: (strings(["s6-svstat", "-o", "up", "/run/service/{}"]), "true".to_string()),
+            list: Listing::Command(strings(["s6-rc-db", "contents", "default"])),
+            commit: strings(["s6-db-reload"]),
+        }
+    }
+
+    pub fn dinit() -> Self {
+        Self {
+            title: ServicesT::Dinit.to_string(),
+            binary: "dinitctl".to_string(),
+            enable: strings(["dinitctl", "enable", "{}"]),
+            disable: strings(["dinitctl", "disable", "{}"]),
+            start: strings(["dinitctl", "start", "{}"]),
+            stop: strings(["dinitctl", "stop", "{}"]),
+            restart: strings(["dinitctl", "restart", "{}"]),
+            status: (strings(["dinitctl", "status", "{}"]), "STARTED".to_string()),
+            list: Listing::Directory(PathBuf::from("/etc/dinit.d/boot.d")),
+            commit: Vec::new(),
+        }
+    }
+
+    fn args(template: &[String], service: &str) -> Vec<String> {
+        template.iter().map(|arg| arg.replace("{}", service)).collect()
+    }
+
+    fn run(&self, template: &[String], services: &[String]) {
+        for service in services {
+            get_buffer("sudo", &Self::args(template, service), &Vec::new());
+        }
+    }
+
+    /// The service names of `rc-update show` or `s6-rc-db contents` output.
+    fn listed(output: &str) -> Vec<String> {
+        output.lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Services enabled at boot.
+    fn enabled(&self) -> Vec<String> {
+        match &self.list {
+            Listing::Command(list) => Self::listed(&checker(&list[0], &list[1..].to_vec())),
+            Listing::Directory(directory) => fs::read_dir(directory)
+                .map(|dir| dir.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect())
+                .unwrap_or_default()
+        }
+    }
+
+    /// Runs through sudo like the other commands: runit's `supervise`
+    /// directories are only readable by root, and dinitctl run as a user talks
+    /// to that user's instance instead of the system one.
+    fn active(&self, service: &str) -> bool {
+        let (status, expected) = &self.status;
+        Command::new("sudo").args(Self::args(status, service)).stderr(Stdio::null()).output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(expected.as_str()))
+    }
+
+    /// Runs `binary` from `path` instead of `PATH`.
+    pub fn locate(mut self, path: &str) -> Self {
+        let listing = match &mut self.list {
+            Listing::Command(list) => Some(list),
+            Listing::Directory(_) => None
+        };
+        for template in [&mut self.enable, &mut self.disable, &mut self.start, &mut self.stop, &mut self.restart, &mut self.status.0, &mut self.commit].into_iter().chain(listing) {
+            for arg in template.iter_mut().filter(|arg| **arg == self.binary) {
+                *arg = path.to_string();
+            }
+        }
+        self.binary = path.to_string();
+        self
+    }
+
+    /// Applies the services under `root`. Without any, services enabled on an
+    /// earlier run are disabled again. Returns why the services were skipped,
+    /// if they were declared but the manager is missing.
+    pub fn enabler(&self, stype: &Value, statements: &mut ServiceStatements) -> Option<String> {
+        let table = stype.get("root");
+        if let Some(reason) = missing_program(&self.binary) {
+            table?;
+            println!("{}",format!("Skipped {}: {reason}",self.title).yellow());
+            return Some(reason)
+        }
+        let enabled = self.enabled();
+        let units: Vec<Unit> = table.map(|table| get_array("root", table)).unwrap_or_default().iter().map(Unit::new).collect();
+        let (mut to_enable, mut to_disable, mut to_start, mut to_stop) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for unit in &units {
+            let is_enabled = enabled.contains(&unit.name);
+            match unit.state {
+                State::Enabled | State::Started => {
+                    if unit.state == State::Enabled && !is_enabled {
+                        to_enable.push(unit.name.to_string());
+                    }
+                    if (unit.now || unit.state == State::Started) && !self.active(&unit.name) {
+                        to_start.push(unit.name.to_string());
+                    }
+                },
+                State::Disabled => {
+                    if is_enabled {
+                        to_disable.push(unit.name.to_string());
+                    }
+                    if unit.now && self.active(&unit.name) {
+                        to_stop.push(unit.name.to_string());
+                    }
+                },
+                State::Masked => {
+                    println!("{}",format!("{} cannot mask {}, leaving it as is.", self.title, unit.name).yellow());
+                }
+            }
+            if statements.update.execute((&self.title, &unit.name)).unwrap() == 0 {
+                statements.insert.execute((&self.title, &unit.name)).unwrap();
+            }
+        }
+
+        let pack_iter = statements.select.query_map([&self.title],|row|{
+            let value:String = row.get(0)?;
+            Ok(value)
+        }).unwrap();
+        for service in pack_iter {
+            let service = service.unwrap();
+            if enabled.contains(&service) {
+                to_disable.push(service);
+            }
+        }
+        statements.remove.execute([&self.title]).unwrap();
+        statements.zero.execute([&self.title]).unwrap();
+
+        self.run(&self.enable, &to_enable);
+        self.run(&self.disable, &to_disable);
+        if !self.commit.is_empty() && (!to_enable.is_empty() || !to_disable.is_empty()) {
+            get_buffer("sudo", &self.commit, &Vec::new());
+        }
+        self.run(&self.start, &to_start);
+        self.run(&self.stop, &to_stop);
+
+        let to_restart: Vec<String> = units.iter()
+            .filter(|unit| matches!(unit.state, State::Enabled | State::Started))
+            .filter(|unit| unit.restart_on.iter().any(|path| changed(path)))
+            .map(|unit| unit.name.to_string())
+            .collect();
+        self.run(&self.restart, &to_restart);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Backend;
+
+    #[test]
+    fn backends_fill_in_services() {
+        let openrc = Backend::openrc();
+        assert_eq!(Backend::args(&openrc.enable, "sshd"), ["rc-update", "add", "sshd", "default"]);
+        assert_eq!(Backend::args(&openrc.start, "sshd"), ["rc-service", "sshd", "start"]);
+        assert_eq!(Backend::listed("                sshd | default\n              chronyd | default\n"), ["sshd", "chronyd"]);
+        let runit = Backend::runit_in("/etc/runit/sv", "/run/runit/service");
+        assert_eq!(Backend::args(&runit.enable, "sshd"), ["ln", "-s", "/etc/runit/sv/sshd", "/run/runit/service/"]);
+        assert_eq!(Backend::args(&runit.start, "sshd"), ["sv", "up", "/run/runit/service/sshd"]);
+        assert_eq!(Backend::args(&runit.status.0, "sshd"), ["sv", "status", "/run/runit/service/sshd"]);
+        let dinit = Backend::dinit().locate("/opt/dinit/bin/dinitctl");
+        assert_eq!(dinit.binary, "/opt/dinit/bin/dinitctl");
+        assert_eq!(Backend::args(&dinit.stop, "sshd"), ["/opt/dinit/bin/dinitctl", "stop", "sshd"]);
+        assert_eq!(Backend::args(&Backend::s6().disable, "sshd"), ["s6-service", "delete", "default", "sshd"]);
+        assert_eq!(Backend::args(&Backend::dinit().restart, "sshd"), ["dinitctl", "restart", "sshd"]);
+    }
+}
@@ -42,6 +42,20 @@ impl ServiceDatabase {
                 to_keep     BOOL
                 );"
         ), ()).unwrap();
+        self.conn.execute(&format!(
+            "CREATE TABLE IF NOT EXISTS Units (
+                manager     TEXT NOT NULL,
+                unit        TEXT NOT NULL,
+                to_keep     BOOL
+                );"
+        ), ()).unwrap();
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS Linger (
+                manager     TEXT NOT NULL,
+                user        TEXT NOT NULL,
+                to_keep     BOOL
+                );"
+        , ()).unwrap();
     }
 }
 
@@ -55,7 +69,18 @@ pub struct ServiceStatements<'conn> {
 
 impl <'conn>ServiceStatements<'conn> {
     pub fn new(conn: &'conn Connection) -> Self {
-        let states = StatementPool::new();
+        Self::prepare(conn, StatementPool::new("Services", "service"))
+    }
+
+    pub fn units(conn: &'conn Connection) -> Self {
+        Self::prepare(conn, StatementPool::new("Units", "unit"))
+    }
+
+    pub fn linger(conn: &'conn Connection) -> Self {
+        Self::prepare(conn, StatementPool::new("Linger", "user"))
+    }
+
+    fn prepare(conn: &'conn Connection, states: StatementPool) -> Self {
         Self {
             insert: conn.prepare(&states.insert).unwrap(),
             update: conn.prepare(&states.update).unwrap(),
@@ -75,29 +100,29 @@ struct StatementPool {
 }
 
 impl StatementPool {
-    fn new() -> Self {
+    fn new(table: &str, column: &str) -> Self {
         Self {
             update: format!(
-                "UPDATE Services
+                "UPDATE {table}
                     SET to_keep = 1
-                    WHERE manager = ?1 AND service = ?2
+                    WHERE manager = ?1 AND {column} = ?2
                 ;"),
             insert: format!(
-                "INSERT INTO Services (manager, service, to_keep)
+                "INSERT INTO {table} (manager, {column}, to_keep)
                     VALUES (?1, ?2, 1)
                 ;"),
             select: format!(
-                "SELECT service
-                FROM Services
+                "SELECT {column}
+                FROM {table}
                 WHERE manager = ?1 AND to_keep = 0
                 ;"),
             zero: format!(
-                "UPDATE Services
+                "UPDATE {table}
                 SET to_keep = 0
                 WHERE to_keep = 1 AND manager = ?1
                 ;"),
             remove: format!(
-                "DELETE FROM Services
+                "DELETE FROM {table}
                 WHERE to_keep = 0 AND manager = ?1
                 ;")
 
@@ -0,0 +1,228 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::{fs, path::{Path, PathBuf}, process::exit};
+use colored::Colorize;
+use toml::Value;
+use users::get_user_by_name;
+
+use shared::Ops;
+
+use crate::manage_data::tools::{get_buffer, get_table, record_change, remove_root, root_command, write_root};
+
+use super::{database::ServiceStatements, services::Services, unit::Unit};
+
+enum UnitFileError {
+    MissingContents(String),
+    InvalidField(String, &'static str),
+    Unreadable(String, PathBuf),
+}
+
+impl fmt::Display for UnitFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::MissingContents(unit) => {
+                writeln!(f, "\tUnit file {} needs a source or content", unit.red())?;
+                writeln!(f, "\tExpected: {}", "\"backup.service\" = { source = \"units/backup.service\" }".yellow())?
+            },
+            Self::InvalidField(unit, field) => {
+                writeln!(f, "\tInvalid {} for unit file {}", field.red(), unit.red())?
+            },
+            Self::Unreadable(unit, source) => {
+                writeln!(f, "\tCould not read {} for unit file {}", source.display().to_string().red(), unit.red())?
+            }
+        }
+        exit(1);
+    }
+}
+
+/// A unit file or drop-in declared under `[services.systemd.units]`. The key
+/// is the path below the systemd unit directory, so `sshd.service.d/override.conf`
+/// is a drop-in for `sshd.service`.
+pub struct UnitFile {
+    pub path: String,
+    pub contents: String,
+    pub user: bool,
+    pub unit: Option<Unit>,
+}
+
+impl UnitFile {
+    pub fn new(path: &str, value: &Value, directory: &Path) -> Self {
+        let table = get_table(path, value);
+        let invalid = |field: &'static str| -> ! {
+            println!("{}",UnitFileError::InvalidField(path.to_string(), field));
+            exit(1)
+        };
+        let contents = match (table.get("content"), table.get("source")) {
+            (Some(Value::String(content)), None) => content.to_string(),
+            (None, Some(Value::String(source))) => {
+                let source = directory.join(source);
+                fs::read_to_string(&source).unwrap_or_else(|_| {
+                    println!("{}",UnitFileError::Unreadable(path.to_string(), source.to_path_buf()));
+                    exit(1)
+                })
+            },
+            (None, None) => {
+                println!("{}",UnitFileError::MissingContents(path.to_string()));
+                exit(1)
+            },
+            (Some(_), _) => invalid("content"),
+            (_, Some(_)) => invalid("source")
+        };
+        let user = match table.get("user") {
+            None => false,
+            Some(Value::Boolean(user)) => *user,
+            Some(_) => invalid("user")
+        };
+        let unit = if Self::dropin(path) {
+            None
+        } else {
+            let mut table = table.clone();
+            table.insert("name".to_string(), Value::from(path.to_string()));
+            Some(Unit::new(&Value::Table(table)))
+        };
+        Self { path: path.to_string(), contents, user, unit }
+    }
+
+    pub fn dropin(path: &str) -> bool {
+        Path::new(path).parent().is_some_and(|parent| parent.to_string_lossy().ends_with(".d"))
+    }
+}
+
+impl Services {
+    /// Unit files and drop-ins of this scope declared under `units`.
+    pub fn unit_files(&self, stype: &Value, directory: &Path) -> Vec<UnitFile> {
+        stype.get("units")
+            .map(|units| get_table("units", units))
+            .unwrap_or_default()
+            .iter()
+            .map(|(path, value)| UnitFile::new(path, value, directory))
+            .filter(|file| file.user == (self.stype == "user"))
+            .collect()
+    }
+
+    /// Writes the unit files, removes the ones tracked under `manager` that are
+    /// no longer declared and reloads systemd when anything changed. Returns
+    /// the units that should be enabled.
+    pub fn deploy(&self, manager: &str, files: Vec<UnitFile>, statements: &mut ServiceStatements) -> Vec<Unit> {
+        let mut changed = false;
+        let mut units = Vec::new();
+        for file in files {
+            let path = self.units.join(&file.path);
+            if fs::read_to_string(&path).ok().as_deref() != Some(file.contents.as_str()) && self.write(&path, &file.contents) {
+                record_change(&path);
+                changed = true;
+            }
+            if statements.update.execute((manager, &file.path)).unwrap() == 0 {
+                statements.insert.execute((manager, &file.path)).unwrap();
+            }
+            units.extend(file.unit);
+        }
+
+        let pack_iter = statements.select.query_map([manager],|row|{
+            let value:String = row.get(0)?;
+            Ok(value)
+        }).unwrap();
+        let dropped: Vec<String> = pack_iter.map(|path| path.unwrap()).collect();
+        let to_disable: Vec<String> = dropped.iter()
+            .filter(|path| !UnitFile::dropin(path))
+            .map(|path| path.to_string())
+            .collect();
+        self.action("disable", true, &to_disable);
+        for path in &dropped {
+            self.delete(&self.units.join(path));
+            changed = true;
+        }
+        statements.remove.execute([manager]).unwrap();
+        statements.zero.execute([manager]).unwrap();
+
+        if changed {
+            get_buffer(&self.manager, &self.args(&["daemon-reload"]), &Vec::new());
+        }
+        units
+    }
+
+    /// Writes a unit file, through declarixRoot unless it belongs to the
+    /// invoking user. Files of another account are handed to that account
+    /// along with any directories created for them.
+    fn write(&self, path: &Path, contents: &str) -> bool {
+        if self.stype == "user" {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+            return true
+        }
+        let created = path.ancestors().skip(1).take_while(|dir| !dir.exists()).last().unwrap_or(path).to_path_buf();
+        if !write_root(path, contents) {
+            return false
+        }
+        match self.owner.as_deref().and_then(get_user_by_name) {
+            Some(owner) => root_command(Ops::Chown, &[Path::new(&format!("{}:{}", owner.uid(), owner.primary_group_id())), &created]),
+            None => true
+        }
+    }
+
+    /// Removes a unit file along with its drop-in directory once it is empty.
+    fn delete(&self, path: &Path) {
+        if self.stype != "user" {
+            remove_root(path);
+        } else if path.exists() {
+            fs::remove_file(path).unwrap();
+        }
+        if let Some(parent) = path.parent().filter(|parent| parent.to_string_lossy().ends_with(".d")) {
+            if parent.read_dir().is_ok_and(|mut dir| dir.next().is_none()) {
+                if self.stype != "user" {
+                    root_command(Ops::Rm_Dir, &[parent]);
+                } else {
+                    fs::remove_dir(parent).unwrap();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::UnitFile;
+    use crate::services::services::{Builder, Systemd, SystemdUser};
+
+    #[test]
+    fn unit_files_read_content_or_source() {
+        let directory = env::temp_dir().join("declarix-unit-files");
+        fs::create_dir_all(directory.join("units")).unwrap();
+        fs::write(directory.join("units/backup.service"), "[Service]\nExecStart=/usr/bin/backup\n").unwrap();
+        let units: toml::Value = toml::from_str(r#"
+            [units]
+            "backup.service" = { source = "units/backup.service", now = true }
+            "sshd.service.d/override.conf" = { content = "[Service]\nRestart=always\n" }
+            "mpd.service" = { content = "[Service]\nExecStart=/usr/bin/mpd\n", user = true, state = "disabled" }
+        "#).unwrap();
+        let backup = UnitFile::new("backup.service", &units["units"]["backup.service"], &directory);
+        assert_eq!(backup.contents, "[Service]\nExecStart=/usr/bin/backup\n");
+        assert!(backup.unit.is_some_and(|unit| unit.now));
+        let dropin = UnitFile::new("sshd.service.d/override.conf", &units["units"]["sshd.service.d/override.conf"], &directory);
+        assert!(dropin.unit.is_none() && !dropin.user);
+        assert!(UnitFile::dropin("sshd.service.d/override.conf"));
+        assert!(!UnitFile::dropin("backup.service"));
+        let paths = |files: Vec<UnitFile>| files.into_iter().map(|file| file.path).collect::<Vec<String>>();
+        assert_eq!(paths(Systemd::new().service.unit_files(&units, &directory)), ["backup.service", "sshd.service.d/override.conf"]);
+        assert_eq!(paths(SystemdUser::new().service.unit_files(&units, &directory)), ["mpd.service"]);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -14,5 +14,9 @@ GNU General Public License for more details.
 You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
+mod backends;
 mod database;
-pub mod services;
\ No newline at end of file
+mod files;
+pub mod services;
+mod timers;
+mod unit;
\ No newline at end of file
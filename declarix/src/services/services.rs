@@ -15,20 +15,26 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
 use core::fmt;
-use std::path::PathBuf;
+use std::{path::{Path, PathBuf}, process::exit};
 
 use colored::Colorize;
-use regex::Regex;
-use toml::Value;
+use dirs::config_dir;
+use rusqlite::Connection;
+use toml::{Table, Value};
+use users::{get_user_by_name, os::unix::UserExt};
 
-use crate::manage_data::tools::{checker, convert_to_string, get_array, get_buffer};
+use crate::{installation::init::{manager_path, report, Failure}, manage_data::tools::{changed, checker, get_array, get_buffer, get_table, missing_program}};
 
-use super::database::{ServiceDatabase, ServiceStatements};
+use super::{backends::Backend, database::{ServiceDatabase, ServiceStatements}, unit::{State, Unit, UnitState}};
 
 #[derive(Debug)]
-enum ServicesT {
+pub enum ServicesT {
     Systemd,
-    SystemdUser
+    SystemdUser,
+    OpenRC,
+    Runit,
+    S6,
+    Dinit,
 }
 
 impl fmt::Display for ServicesT {
@@ -37,63 +43,133 @@ impl fmt::Display for ServicesT {
     }
 }
 
+enum ServiceError {
+    UnknownManager(String),
+    UnknownUser(String),
+    InvalidLinger(String),
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::UnknownManager(manager) => {
+                writeln!(f, "\tUnknown service manager: {}", manager.red())?;
+                writeln!(f, "\tSupported: {}", "systemd, openrc, runit, s6, dinit".yellow())?
+            },
+            Self::UnknownUser(user) => {
+                writeln!(f, "\tUser {} does not exist", user.red())?
+            },
+            Self::InvalidLinger(user) => {
+                writeln!(f, "\tInvalid linger for user {}", user.red())?;
+                writeln!(f, "\tExpected: {}", "linger = true".yellow())?
+            }
+        }
+        exit(1);
+    }
+}
+
 pub struct Services {
     pub title: String,
     pub manager: String,
     pub stype: String,
-    pub enable: Vec<String>,
-    pub disable: Vec<String>,
-    pub list: Vec<String>,
-
+    pub command: Vec<String>,
+    pub query: Vec<String>,
+    pub units: PathBuf,
+    pub owner: Option<String>,
 }
 
 impl Services {
-    fn new<'a>(manager: &'a str, title: ServicesT, stype: &'a str, enable: impl IntoIterator<Item = &'a str>, disable: impl IntoIterator<Item = &'a str>, list: impl IntoIterator<Item = &'a str>) -> Self {
+    fn new<'a>(manager: &'a str, title: ServicesT, stype: &'a str, command: impl IntoIterator<Item = &'a str>, query: impl IntoIterator<Item = &'a str>, units: PathBuf) -> Self {
         Self {
             title: title.to_string(),
             manager: manager.to_string(),
             stype: stype.to_string(),
-            enable: enable.into_iter().map(String::from).collect(),
-            disable: disable.into_iter().map(String::from).collect(),
-            list: list.into_iter().map(String::from).collect(),
+            command: command.into_iter().map(String::from).collect(),
+            query: query.into_iter().map(String::from).collect(),
+            units,
+            owner: None,
         }
     }
-    fn enable(&self, prog: &Vec<String>) {
-        get_buffer(&self.manager, &self.enable, prog)
+
+    pub fn args(&self, args: &[&str]) -> Vec<String> {
+        self.command.iter().cloned().chain(args.iter().map(|arg| arg.to_string())).collect()
+    }
+
+    pub fn action(&self, action: &str, now: bool, services: &Vec<String>) {
+        if !services.is_empty() {
+            let now = if now { vec!["--now"] } else { Vec::new() };
+            get_buffer(&self.manager, &self.args(&[[action].as_slice(), &now].concat()), services)
+        }
     }
 
-    fn disable(&self, prog: &Vec<String>) {
-        get_buffer(&self.manager, &self.disable, prog);
+    /// Queries systemd for the state of a single unit.
+    pub fn state(&self, unit: &Unit) -> UnitState {
+        let mut query = self.query.clone();
+        query.extend(["show", "-p", "UnitFileState", "-p", "ActiveState"].map(String::from));
+        query.push(unit.unit());
+        UnitState::new(&checker(&query[0], &query[1..].to_vec()))
     }
 
-    fn enabler (&self, name: &str, stype: &Value, statements: &mut ServiceStatements) {
+    /// Applies the units of this scope. A scope without any runs as well, so
+    /// whatever it deployed or enabled before is cleaned up.
+    /// Returns why the scope was skipped, if it was declared but `name` is missing.
+    fn enabler (&self, name: &str, stype: &Value, directory: &Path, statements: &mut ServiceStatements, files: &mut ServiceStatements) -> Option<String> {
+        let declared = stype.get(&self.stype).is_some() || stype.get("units").is_some();
+        if let Some(reason) = missing_program(name) {
+            if !declared {
+                return None
+            }
+            println!("{}",format!("Skipped {}: {reason}",self.title).yellow());
+            return Some(reason)
+        }
+        let mut units = self.deploy(&self.title, self.unit_files(stype, directory), files);
         if let Some(table) = stype.get(&self.stype) {
-            if PathBuf::from(format!("/usr/bin/{}",name)).exists() {
-                let check = format!("{:?}",checker(&self.manager, &self.list));
-                let services = convert_to_string(&get_array(&self.stype, &table));
-                let mut to_enable = Vec::new();
-                for service in services {
-                    if !self.enabled(&check, &service) {
-                        to_enable.push(service.to_string());
-                    }
-                    if statements.update.execute((&self.title, &service)).unwrap() == 0 {
-                        statements.insert.execute((&self.title, &service)).unwrap();
-                    }
-                }
-                if !to_enable.is_empty() {
-                    self.enable(&to_enable);
-                }
-                self.disabler(statements);
-            } else {
-                println!("{}",format!("{} is not installed on your system.",name).red())
+            units.extend(get_array(&self.stype, table).iter().map(Unit::new));
+        }
+        let mut to_unmask = Vec::new();
+        let mut actions: Vec<(&str, bool, Vec<String>)> = Vec::new();
+        let mut queue = |action: &'static str, now: bool, unit: &Unit| {
+            match actions.iter_mut().find(|(a, n, _)| *a == action && *n == now) {
+                Some((_, _, services)) => services.push(unit.unit()),
+                None => actions.push((action, now, vec![unit.unit()]))
+            }
+        };
+        for unit in &units {
+            let state = self.state(unit);
+            if unit.state != State::Masked && state.masked() {
+                to_unmask.push(unit.unit());
             }
+            match unit.state {
+                State::Enabled if !state.enabled() && !state.fixed() => queue("enable", unit.now, unit),
+                State::Enabled if unit.now && !state.active() => queue("start", false, unit),
+                State::Started if !state.active() => queue("start", false, unit),
+                State::Disabled if state.enabled() => queue("disable", unit.now, unit),
+                State::Disabled if unit.now && state.active() => queue("stop", false, unit),
+                State::Masked if !state.masked() => queue("mask", unit.now, unit),
+                _ => {}
+            }
+            if statements.update.execute((&self.title, &unit.name)).unwrap() == 0 {
+                statements.insert.execute((&self.title, &unit.name)).unwrap();
+            }
+        }
+        self.action("unmask", false, &to_unmask);
+        for (action, now, services) in &actions {
+            self.action(action, *now, services);
         }
+        self.disabler(statements);
+        self.restarter(&units);
+        None
     }
-    fn enabled(&self, enabled: &str, mtch: &str) -> bool {
-        let reg = "\\\\n|.service";
-        let reg = &format!("({}|^){}({}|$)",&reg,&mtch,&reg);
-        let re = Regex::new(reg).unwrap();
-        return re.is_match(enabled);
+
+    /// Runs `systemctl` from `path` instead of `PATH`.
+    pub fn locate(mut self, path: &str) -> Self {
+        for arg in [&mut self.manager].into_iter().chain(&mut self.command).chain(&mut self.query) {
+            if arg == "systemctl" {
+                *arg = path.to_string();
+            }
+        }
+        self
     }
 
     fn disabler(&self, statements: &mut ServiceStatements) {
@@ -102,23 +178,41 @@ impl Services {
             Ok(value)
         }).unwrap();
         let mut to_disable = Vec::new();
+        let mut to_unmask = Vec::new();
         for service in pack_iter {
-            to_disable.push(service.unwrap());
-        }
-        if !to_disable.is_empty() {
-            self.disable(&to_disable);
+            let unit = Unit::new(&Value::String(service.unwrap()));
+            let state = self.state(&unit);
+            if state.enabled() {
+                to_disable.push(unit.unit());
+            }
+            if state.masked() {
+                to_unmask.push(unit.unit());
+            }
         }
+        self.action("disable", false, &to_disable);
+        self.action("unmask", false, &to_unmask);
         statements.remove.execute([&self.title]).unwrap();
         statements.zero.execute([&self.title]).unwrap();
     }
+
+    /// Reloads or restarts the services whose `restart_on` paths were linked or
+    /// copied during this run.
+    fn restarter(&self, units: &[Unit]) {
+        let to_restart: Vec<String> = units.iter()
+            .filter(|unit| matches!(unit.state, State::Enabled | State::Started))
+            .filter(|unit| unit.restart_on.iter().any(|path| changed(path)))
+            .map(Unit::unit)
+            .collect();
+        self.action("reload-or-restart", false, &to_restart);
+    }
 }
 
 pub struct Systemd {
-    service: Services,
+    pub service: Services,
 }
 
 pub struct SystemdUser {
-    service: Services,
+    pub service: Services,
 }
 
 pub trait Builder {
@@ -132,9 +226,9 @@ impl Builder for Systemd {
             service: Services::new("sudo",
             ServicesT::Systemd,
             "root",
-            [service, "enable"], 
-            [service, "disable"],
-            [service, "list-unit-files", "--state=enabled"])
+            [service],
+            [service],
+            PathBuf::from("/etc/systemd/system"))
         }
     }
 }
@@ -142,39 +236,198 @@ impl Builder for Systemd {
 impl Builder for SystemdUser {
     fn new() -> Self {
         Self {
-            service: Services::new("systemctl", 
+            service: Services::new("systemctl",
             ServicesT::SystemdUser,
             "user",
-            ["--user", "enable"],
-            ["--user", "disable"],
-            ["list-unit-files", "--user", "--state=enabled"])
+            ["--user"],
+            ["systemctl", "--user"],
+            config_dir().unwrap().join("systemd").join("user"))
         }
     }
 }
 
-pub struct Service {
+const LINGER: &str = "SystemdUser";
 
+/// User services of another account, managed through `systemctl --user -M <user>@`.
+pub struct SystemdOther {
+    pub service: Services,
+    pub user: String,
+}
+
+impl SystemdOther {
+    pub fn new(user: &str) -> Self {
+        let machine = format!("{user}@");
+        let home = get_user_by_name(user).map(|user| user.home_dir().to_path_buf()).unwrap_or_else(|| {
+            println!("{}",ServiceError::UnknownUser(user.to_string()));
+            exit(1)
+        });
+        let mut service = Services::new("sudo",
+            ServicesT::SystemdUser,
+            "services",
+            ["systemctl", "--user", "-M", &machine],
+            ["sudo", "systemctl", "--user", "-M", &machine],
+            home.join(".config").join("systemd").join("user"));
+        service.title = format!("{}:{}", service.title, user);
+        service.owner = Some(user.to_string());
+        Self { service, user: user.to_string() }
+    }
+
+    /// Turns lingering on or off when `linger` is set, so the user's services
+    /// run without them being logged in. Lingering that was turned on is
+    /// tracked so it can be turned off once the user is dropped.
+    fn linger(&self, table: &Value, lingering: &mut ServiceStatements) {
+        let Some(linger) = table.get("linger") else {
+            return
+        };
+        let Some(linger) = linger.as_bool() else {
+            println!("{}",ServiceError::InvalidLinger(self.user.to_string()));
+            exit(1)
+        };
+        Self::set_linger(&self.user, linger);
+        if linger && lingering.update.execute((LINGER, &self.user)).unwrap() == 0 {
+            lingering.insert.execute((LINGER, &self.user)).unwrap();
+        }
+    }
+
+    fn set_linger(user: &str, linger: bool) {
+        if Path::new("/var/lib/systemd/linger").join(user).exists() != linger {
+            let action = if linger { "enable-linger" } else { "disable-linger" };
+            get_buffer("sudo", &vec!["loginctl".to_string(), action.to_string()], &vec![user.to_string()]);
+        }
+    }
+}
+
+pub struct Service {
+    pub directory: PathBuf,
+    pub managers: Table,
+    pub skipped: Vec<Failure>,
 }
 
 impl Service {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            directory: PathBuf::from("/etc/declarix"),
+            managers: Table::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Where `systemctl` is found, honouring `[managers.systemd] path`.
+    pub fn systemctl(&self) -> String {
+        manager_path(&self.managers, "systemd").unwrap_or("systemctl".to_string())
     }
-    pub fn match_service(&self, title: &str, stype: &Value) {
+
+    pub fn systemd(&self) -> Services {
+        Systemd::new().service.locate(&self.systemctl())
+    }
+
+    pub fn systemd_user(&self) -> Services {
+        SystemdUser::new().service.locate(&self.systemctl())
+    }
+
+    fn backend(&self, title: &str, backend: Backend) -> Backend {
+        match manager_path(&self.managers, title) {
+            Some(path) => backend.locate(&path),
+            None => backend
+        }
+    }
+
+    pub fn skip(&mut self, title: &str, reason: Option<String>) {
+        if let Some(message) = reason {
+            self.skipped.push(Failure { title: title.to_string(), packages: Vec::new(), message, skipped: true });
+        }
+    }
+
+    /// Lists the service managers that were declared but could not run.
+    pub fn report(&self) {
+        report(&self.skipped);
+    }
+    /// Runs the service managers missing from `[services]`, so that what they
+    /// managed before is disabled and removed.
+    pub fn dropped(&mut self, services: Option<&Value>, only: &[String]) {
+        let declared: Vec<String> = services.map(|services| get_table("services", services)).unwrap_or_default()
+            .keys().map(|title| title.to_lowercase()).collect();
+        for title in ["systemd", "openrc", "runit", "s6", "dinit"] {
+            if !declared.iter().any(|declared| declared == title) && (only.is_empty() || only.iter().any(|only| only == title)) {
+                self.match_service(title, &Value::Table(Table::new()));
+            }
+        }
+    }
+
+    pub fn match_service(&mut self, title: &str, stype: &Value) {
         let db = ServiceDatabase::new();
         db.conn.execute("BEGIN TRANSACTION", ()).unwrap();
         db.create_table();
         let mut statements = ServiceStatements::new(&db.conn);
+        let mut files = ServiceStatements::units(&db.conn);
         let title = title.to_lowercase();
         match title.as_str() {
             "systemd" => {
-                SystemdUser::new().service.enabler("systemctl", stype, &mut statements);
-                Systemd::new().service.enabler("systemctl", stype, &mut statements);
+                let systemctl = self.systemctl();
+                let user = self.systemd_user();
+                let skipped = user.enabler(&systemctl, stype, &self.directory, &mut statements, &mut files);
+                self.skip(&user.title, skipped);
+                let root = self.systemd();
+                let skipped = root.enabler(&systemctl, stype, &self.directory, &mut statements, &mut files);
+                self.skip(&root.title, skipped);
+                let mut lingering = ServiceStatements::linger(&db.conn);
+                self.users(stype, &db.conn, &mut statements, &mut files, &mut lingering);
+            },
+            "openrc" | "runit" | "s6" | "dinit" => {
+                let backend = match title.as_str() {
+                    "openrc" => Backend::openrc(),
+                    "runit" => Backend::runit(),
+                    "s6" => Backend::s6(),
+                    _ => Backend::dinit(),
+                };
+                let backend = self.backend(&title, backend);
+                let skipped = backend.enabler(stype, &mut statements);
+                self.skip(&backend.title, skipped);
             },
             &_ => {
-
+                println!("{}",ServiceError::UnknownManager(title.to_string()));
             }
         }
         db.conn.execute("COMMIT TRANSACTION", ()).unwrap();
     }
+
+    /// Manages `[services.systemd.users.<user>]`. Users that were removed from
+    /// the config have their services disabled, their unit files removed and
+    /// lingering turned off again.
+    fn users(&mut self, stype: &Value, conn: &Connection, statements: &mut ServiceStatements, files: &mut ServiceStatements, lingering: &mut ServiceStatements) {
+        let users = stype.get("users").map(|users| get_table("users", users)).unwrap_or_default();
+        let systemctl = self.systemctl();
+        let mut declared = Vec::new();
+        for (user, table) in &users {
+            let mut other = SystemdOther::new(user);
+            other.service = other.service.locate(&systemctl);
+            other.linger(table, lingering);
+            let skipped = other.service.enabler(&systemctl, table, &self.directory, statements, files);
+            self.skip(&other.service.title, skipped);
+            declared.push(other.service.title);
+        }
+        let prefix = format!("{}:", ServicesT::SystemdUser);
+        let mut managers = conn.prepare("SELECT manager FROM Services WHERE manager LIKE ?1 UNION SELECT manager FROM Units WHERE manager LIKE ?1").unwrap();
+        let dropped: Vec<String> = managers.query_map([format!("{prefix}%")], |row| row.get(0)).unwrap()
+            .map(|manager| manager.unwrap())
+            .filter(|manager| !declared.contains(manager))
+            .collect();
+        for manager in dropped {
+            if let Some(user) = manager.strip_prefix(&prefix).filter(|user| get_user_by_name(user).is_some()) {
+                let mut other = SystemdOther::new(user);
+                other.service = other.service.locate(&systemctl);
+                other.service.deploy(&manager, Vec::new(), files);
+                other.service.disabler(statements);
+            }
+        }
+        let pack_iter = lingering.select.query_map([LINGER],|row|{
+            let value:String = row.get(0)?;
+            Ok(value)
+        }).unwrap();
+        for user in pack_iter.map(|user| user.unwrap()).filter(|user| get_user_by_name(user).is_some()) {
+            SystemdOther::set_linger(&user, false);
+        }
+        lingering.remove.execute([LINGER]).unwrap();
+        lingering.zero.execute([LINGER]).unwrap();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,207 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::process::exit;
+use colored::Colorize;
+use toml::Value;
+
+use crate::manage_data::tools::{changed, get_table, missing_program};
+
+use super::{database::{ServiceDatabase, ServiceStatements}, files::UnitFile, services::{Service, Services}, unit::{State, Unit}};
+
+enum TimerError {
+    MissingField(String, &'static str),
+    InvalidField(String, &'static str),
+}
+
+impl fmt::Display for TimerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::MissingField(timer, field) => {
+                writeln!(f, "\tTimer {} needs a {}", timer.red(), field.red())?;
+                writeln!(f, "\tExpected: {}", "[timers.backup] command = \"/usr/local/bin/backup\", schedule = \"daily\"".yellow())?
+            },
+            Self::InvalidField(timer, field) => {
+                writeln!(f, "\tInvalid {} for timer {}", field.red(), timer.red())?
+            }
+        }
+        exit(1);
+    }
+}
+
+/// A job declared under `[timers.<name>]`, run by a generated
+/// `<name>.service` on the `<name>.timer` schedule.
+pub struct Timer {
+    pub name: String,
+    pub command: String,
+    pub schedule: String,
+    pub user: bool,
+    pub persistent: bool,
+    pub environment: Vec<(String, String)>,
+}
+
+impl Timer {
+    pub fn new(name: &str, value: &Value) -> Self {
+        let table = get_table(name, value);
+        let invalid = |field: &'static str| -> ! {
+            println!("{}",TimerError::InvalidField(name.to_string(), field));
+            exit(1)
+        };
+        let string = |field: &'static str| match table.get(field) {
+            Some(Value::String(value)) => value.to_string(),
+            Some(_) => invalid(field),
+            None => {
+                println!("{}",TimerError::MissingField(name.to_string(), field));
+                exit(1)
+            }
+        };
+        let boolean = |field: &'static str, default: bool| match table.get(field) {
+            None => default,
+            Some(Value::Boolean(value)) => *value,
+            Some(_) => invalid(field)
+        };
+        let environment = match table.get("environment") {
+            None => Vec::new(),
+            Some(Value::Table(environment)) => environment.iter()
+                .map(|(key, value)| (key.to_string(), value.as_str().map(String::from).unwrap_or_else(|| invalid("environment"))))
+                .collect(),
+            Some(_) => invalid("environment")
+        };
+        Self {
+            name: name.to_string(),
+            command: string("command"),
+            schedule: string("schedule"),
+            user: boolean("user", false),
+            persistent: boolean("persistent", true),
+            environment,
+        }
+    }
+
+    /// The command runs through `/bin/sh -c`, so pipes and redirects work as in
+    /// a crontab. `%` is doubled, as systemd would expand it as a specifier, and
+    /// `$` is doubled so that the shell, not systemd, expands variables.
+    fn service(&self) -> String {
+        let quote = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%");
+        let command = quote(&self.command).replace('$', "$$");
+        let mut service = format!("[Unit]\nDescription=declarix job {}\n\n[Service]\nType=oneshot\nExecStart=/bin/sh -c \"{command}\"\n", self.name);
+        for (key, value) in &self.environment {
+            service.push_str(&format!("Environment=\"{key}={}\"\n", quote(value)));
+        }
+        service
+    }
+
+    fn timer(&self) -> String {
+        format!("[Unit]\nDescription=declarix timer for {}\n\n[Timer]\nOnCalendar={}\nPersistent={}\n\n[Install]\nWantedBy=timers.target\n",
+            self.name, self.schedule, self.persistent)
+    }
+
+    /// The generated units. Only the timer is enabled, and started right away.
+    pub fn files(&self) -> Vec<UnitFile> {
+        let timer = Unit {
+            name: format!("{}.timer", self.name),
+            state: State::Enabled,
+            now: true,
+            restart_on: Vec::new()
+        };
+        vec![
+            UnitFile { path: format!("{}.service", self.name), contents: self.service(), user: self.user, unit: None },
+            UnitFile { path: format!("{}.timer", self.name), contents: self.timer(), user: self.user, unit: Some(timer) },
+        ]
+    }
+}
+
+impl Services {
+    /// Deploys the timers of this scope, enabling new ones and restarting
+    /// those whose schedule changed.
+    fn timers(&self, timers: &[Timer], statements: &mut ServiceStatements) {
+        let files = timers.iter()
+            .filter(|timer| timer.user == (self.stype == "user"))
+            .flat_map(Timer::files)
+            .collect();
+        let units = self.deploy(&format!("{}Timers", self.title), files, statements);
+        let mut to_enable = Vec::new();
+        let mut to_restart = Vec::new();
+        for unit in units {
+            let state = self.state(&unit);
+            if !state.enabled() || !state.active() {
+                to_enable.push(unit.unit());
+            } else if changed(&self.units.join(unit.unit())) {
+                to_restart.push(unit.unit());
+            }
+        }
+        self.action("enable", true, &to_enable);
+        self.action("restart", false, &to_restart);
+    }
+}
+
+impl Service {
+    pub fn timers(&mut self, timers: Option<&Value>) {
+        if let Some(reason) = missing_program(&self.systemctl()) {
+            if timers.is_some() {
+                println!("{}",format!("Skipped timers: {reason}").yellow());
+                self.skip("Timers", Some(reason));
+            }
+            return
+        }
+        let db = ServiceDatabase::new();
+        db.conn.execute("BEGIN TRANSACTION", ()).unwrap();
+        db.create_table();
+        let mut statements = ServiceStatements::units(&db.conn);
+        let timers: Vec<Timer> = timers
+            .map(|timers| get_table("timers", timers))
+            .unwrap_or_default()
+            .iter()
+            .map(|(name, timer)| Timer::new(name, timer))
+            .collect();
+        self.systemd_user().timers(&timers, &mut statements);
+        self.systemd().timers(&timers, &mut statements);
+        db.conn.execute("COMMIT TRANSACTION", ()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timer;
+
+    #[test]
+    fn timers_render_units() {
+        let timer: toml::Value = toml::from_str(r#"
+            command = "/usr/local/bin/backup --quiet"
+            schedule = "*-*-* 03:00:00"
+            environment = { RESTIC_REPOSITORY = "/mnt/backup" }
+        "#).unwrap();
+        let files = Timer::new("backup", &timer).files();
+        assert_eq!(files[0].path, "backup.service");
+        assert_eq!(files[0].contents, "[Unit]\nDescription=declarix job backup\n\n[Service]\nType=oneshot\nExecStart=/bin/sh -c \"/usr/local/bin/backup --quiet\"\nEnvironment=\"RESTIC_REPOSITORY=/mnt/backup\"\n");
+        assert!(files[0].unit.is_none() && !files[0].user);
+        assert_eq!(files[1].contents, "[Unit]\nDescription=declarix timer for backup\n\n[Timer]\nOnCalendar=*-*-* 03:00:00\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n");
+        assert_eq!(files[1].unit.as_ref().unwrap().name, "backup.timer");
+    }
+
+    #[test]
+    fn timer_commands_are_escaped() {
+        let timer: toml::Value = toml::from_str(r#"
+            command = 'tar czf "/mnt/backup/$(date +%F).tar.gz" ~/notes 2>&1 | logger'
+            schedule = "daily"
+            environment = { FORMAT = "%Y-%m" }
+        "#).unwrap();
+        let service = &Timer::new("notes", &timer).files()[0].contents;
+        assert!(service.contains("ExecStart=/bin/sh -c \"tar czf \\\"/mnt/backup/$$(date +%%F).tar.gz\\\" ~/notes 2>&1 | logger\"\n"));
+        assert!(service.contains("Environment=\"FORMAT=%%Y-%%m\"\n"));
+    }
+}
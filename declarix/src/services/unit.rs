@@ -0,0 +1,194 @@
+/*
+Copyright (C) 2024  StarlightStargaze
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+use core::fmt;
+use std::{path::PathBuf, process::exit};
+use colored::Colorize;
+use toml::Value;
+
+enum UnitError {
+    MissingName(String),
+    InvalidField(String, &'static str),
+    InvalidState(String, String),
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}:","Error".red())?;
+        match self {
+            Self::MissingName(unit) => {
+                writeln!(f, "\tService entry without a name: {}", unit.red())?;
+                writeln!(f, "\tExpected: {}", "{ name = \"sshd\", state = \"enabled\" }".yellow())?
+            },
+            Self::InvalidField(unit, field) => {
+                writeln!(f, "\tInvalid {} for service {}", field.red(), unit.red())?
+            },
+            Self::InvalidState(unit, state) => {
+                writeln!(f, "\tInvalid state {} for service {}", state.red(), unit.red())?;
+                writeln!(f, "\tExpected one of: {}", "enabled, started, disabled, masked".yellow())?
+            }
+        }
+        exit(1);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum State {
+    Enabled,
+    Started,
+    Disabled,
+    Masked,
+}
+
+/// A single entry of a `[services.<manager>]` array, either `"name"` or
+/// `{ name, state, now, restart_on }`.
+pub struct Unit {
+    pub name: String,
+    pub state: State,
+    pub now: bool,
+    pub restart_on: Vec<PathBuf>,
+}
+
+impl Unit {
+    pub fn new(value: &Value) -> Self {
+        let name = Self::name(value);
+        let Value::Table(table) = value else {
+            return Self {
+                name,
+                state: State::Enabled,
+                now: false,
+                restart_on: Vec::new()
+            }
+        };
+        let invalid = |field: &'static str| -> ! {
+            println!("{}",UnitError::InvalidField(name.to_string(), field));
+            exit(1)
+        };
+        let state = match table.get("state") {
+            None => State::Enabled,
+            Some(Value::String(state)) => match state.as_str() {
+                "enabled" => State::Enabled,
+                "started" => State::Started,
+                "disabled" => State::Disabled,
+                "masked" => State::Masked,
+                _ => {
+                    println!("{}",UnitError::InvalidState(name.to_string(), state.to_string()));
+                    exit(1)
+                }
+            },
+            Some(_) => invalid("state")
+        };
+        let now = match table.get("now") {
+            None => false,
+            Some(Value::Boolean(now)) => *now,
+            Some(_) => invalid("now")
+        };
+        let restart_on = match table.get("restart_on") {
+            None => Vec::new(),
+            Some(Value::Array(paths)) => paths.iter()
+                .map(|path| path.as_str().map(PathBuf::from).unwrap_or_else(|| invalid("restart_on")))
+                .collect(),
+            Some(_) => invalid("restart_on")
+        };
+        Self { name, state, now, restart_on }
+    }
+
+    pub fn name(value: &Value) -> String {
+        match value {
+            Value::String(name) => name.to_string(),
+            Value::Table(table) => match table.get("name") {
+                Some(Value::String(name)) => name.to_string(),
+                _ => {
+                    println!("{}",UnitError::MissingName(value.to_string()));
+                    exit(1)
+                }
+            },
+            _ => {
+                println!("{}",UnitError::MissingName(value.to_string()));
+                exit(1)
+            }
+        }
+    }
+
+    /// The full unit name. Names without a unit type, including template
+    /// instances like `getty@tty1`, are services.
+    pub fn unit(&self) -> String {
+        let types = [".service", ".socket", ".timer", ".path", ".mount", ".automount", ".swap", ".target", ".slice", ".scope"];
+        if types.iter().any(|t| self.name.ends_with(t)) {
+            self.name.to_string()
+        } else {
+            format!("{}.service", self.name)
+        }
+    }
+}
+
+/// The state systemd reports for a single unit through
+/// `systemctl show -p UnitFileState -p ActiveState`.
+#[derive(Default)]
+pub struct UnitState {
+    pub file: String,
+    pub active: String,
+}
+
+impl UnitState {
+    pub fn new(show: &str) -> Self {
+        let mut state = Self::default();
+        for line in show.lines() {
+            match line.split_once('=') {
+                Some(("UnitFileState", value)) => state.file = value.to_string(),
+                Some(("ActiveState", value)) => state.active = value.to_string(),
+                _ => {}
+            }
+        }
+        state
+    }
+
+    pub fn enabled(&self) -> bool {
+        matches!(self.file.as_str(), "enabled" | "enabled-runtime" | "alias")
+    }
+
+    /// Static, indirect and generated units cannot be enabled or disabled
+    /// themselves.
+    pub fn fixed(&self) -> bool {
+        matches!(self.file.as_str(), "static" | "indirect" | "generated")
+    }
+
+    pub fn masked(&self) -> bool {
+        self.file.starts_with("masked")
+    }
+
+    pub fn active(&self) -> bool {
+        matches!(self.active.as_str(), "active" | "reloading" | "activating")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Unit, UnitState};
+
+    #[test]
+    fn units_are_matched_exactly() {
+        let units: toml::Value = toml::from_str(r#"
+            units = ["pipewire", "pipewire-pulse.socket", "getty@tty1", "fstrim.timer"]
+        "#).unwrap();
+        let units: Vec<String> = units["units"].as_array().unwrap().iter().map(|unit| Unit::new(unit).unit()).collect();
+        assert_eq!(units, ["pipewire.service", "pipewire-pulse.socket", "getty@tty1.service", "fstrim.timer"]);
+
+        let state = UnitState::new("ActiveState=active\nUnitFileState=masked-runtime\n");
+        assert!(state.masked() && state.active() && !state.enabled());
+        assert!(UnitState::new("UnitFileState=static\nActiveState=inactive\n").fixed());
+    }
+}
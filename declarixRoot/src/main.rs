@@ -16,8 +16,10 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::{env, fs::hard_link, os::unix::fs::symlink};
+use std::{env, fs::hard_link, os::unix::fs::symlink, process::{exit, Command}};
 use shared::copy_file;
+use shared::move_path;
+use shared::chown_tree;
 use shared::Ops;
 fn main()  {
     let args: Vec<String> = env::args().collect();
@@ -39,7 +41,25 @@ fn main()  {
         }, 
         Ops::Rm_File => {
             std::fs::remove_file(&args[2]).unwrap();
+        },
+        Ops::Create_Dir_All => {
+            std::fs::create_dir_all(&args[2]).unwrap();
+        },
+        Ops::Move => {
+            move_path(&PathBuf::from(&args[2]), &PathBuf::from(&args[3])).unwrap();
+        },
+        Ops::Chown => {
+            let (uid, gid) = args[2].split_once(':').unwrap();
+            chown_tree(&PathBuf::from(&args[3]), uid.parse().unwrap(), gid.parse().unwrap()).unwrap();
+        },
+        Ops::Account => {
+            match args[2].as_str() {
+                "useradd" | "usermod" | "userdel" | "groupadd" | "groupmod" | "groupdel" => {
+                    let status = Command::new(&args[2]).args(&args[3..]).status().unwrap();
+                    exit(status.code().unwrap_or(1))
+                },
+                command => panic!("{command} is not an account command")
+            }
         }
-        _ => {}
     }
 }
\ No newline at end of file
@@ -14,7 +14,7 @@ GNU General Public License for more details.
 You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>.
 */
-use std::{io, fs, path::Path, str::FromStr, fmt::Display};
+use std::{io, fs, os::unix::fs::{lchown, symlink}, path::Path, str::FromStr, fmt::Display};
 
 use filetime::{set_file_mtime, FileTime};
 
@@ -26,6 +26,48 @@ pub fn copy_file<T:AsRef<Path>>(source: &T, destination: &T) -> Result<(), io::E
     Ok(())
 }
 
+pub fn move_path<T:AsRef<Path>>(source: &T, destination: &T) -> Result<(), io::Error> {
+    match fs::rename(source, destination) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            copy_tree(source.as_ref(), destination.as_ref())?;
+            if fs::symlink_metadata(source)?.is_dir() {
+                fs::remove_dir_all(source)
+            } else {
+                fs::remove_file(source)
+            }
+        },
+        result => result
+    }
+}
+
+fn copy_tree(source: &Path, destination: &Path) -> Result<(), io::Error> {
+    let met = fs::symlink_metadata(source)?;
+    if met.is_symlink() {
+        symlink(fs::read_link(source)?, destination)?;
+    } else if met.is_dir() {
+        fs::create_dir(destination)?;
+        fs::set_permissions(destination, met.permissions())?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &destination.join(entry.file_name()))?;
+        }
+    } else {
+        copy_file(&source, &destination)?;
+    }
+    Ok(())
+}
+
+/// Hands `path` and everything below it to `uid` and `gid`.
+pub fn chown_tree(path: &Path, uid: u32, gid: u32) -> Result<(), io::Error> {
+    lchown(path, Some(uid), Some(gid))?;
+    if fs::symlink_metadata(path)?.is_dir() {
+        for entry in fs::read_dir(path)? {
+            chown_tree(&entry?.path(), uid, gid)?;
+        }
+    }
+    Ok(())
+}
+
 #[allow(non_camel_case_types)]
 #[derive(Debug)]
 pub enum Ops {
@@ -35,7 +77,10 @@ pub enum Ops {
     Create_Dir_All,
     Hardlink,
     Rm_File,
-    Rm_Dir
+    Rm_Dir,
+    Move,
+    Chown,
+    Account
 }
 
 impl FromStr for Ops {
@@ -50,6 +95,9 @@ impl FromStr for Ops {
         "Hardlink" => Ok(Ops::Hardlink),
         "Rm_Dir" => Ok(Ops::Rm_Dir),
         "Rm_File" => Ok(Ops::Rm_File),
+        "Move" => Ok(Ops::Move),
+        "Chown" => Ok(Ops::Chown),
+        "Account" => Ok(Ops::Account),
         _ => Err(()),
         }
     }